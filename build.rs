@@ -0,0 +1,30 @@
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map_or("unknown".to_string(), |commit| commit.trim().to_string());
+
+    let timestamp = env::var("SOURCE_DATE_EPOCH").unwrap_or_else(|_| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs())
+            .to_string()
+    });
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(str::to_lowercase))
+        .collect();
+
+    features.sort();
+
+    println!("cargo:rustc-env=DOORMAN_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=DOORMAN_BUILD_TIMESTAMP={}", timestamp);
+    println!("cargo:rustc-env=DOORMAN_FEATURES={}", features.join(","));
+}
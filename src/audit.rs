@@ -0,0 +1,74 @@
+use super::config;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs4::FileExt;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Something worth answering for later, as kept in datadir/audit.jsonl
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditEvent {
+    pub time: DateTime<Utc>,
+
+    /// i.e. launch, configure, nightly, switch_user, guest, or kick
+    pub event: String,
+
+    /// Who actually ran doorman
+    pub caller: String,
+
+    /// Who they were acting as; differs from caller after a user switch
+    pub user: String,
+
+    pub door: Option<String>,
+    pub node: Option<i8>,
+
+    /// Anything else about what happened, i.e. who got kicked or why a command failed
+    pub detail: Option<String>,
+}
+
+fn audit_path(config: &config::Config) -> PathBuf {
+    config.datadir.join("audit.jsonl")
+}
+
+fn append(config: &config::Config, event: &AuditEvent) -> Result<()> {
+    let path = audit_path(config);
+    let mut file = fs::File::options()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Couldn't open audit log {}", path.display()))?;
+
+    file.lock_exclusive()?;
+    writeln!(file, "{}", serde_json::to_string(event)?)?;
+
+    Ok(())
+}
+
+/// Appends an event to the audit log; failing to is only worth a warning
+pub fn record(
+    config: &config::Config,
+    event: &str,
+    door: Option<&str>,
+    node: Option<i8>,
+    detail: Option<String>,
+) {
+    let event = AuditEvent {
+        time: Utc::now(),
+        event: event.to_string(),
+        caller: config
+            .impersonated_by
+            .clone()
+            .unwrap_or_else(|| config.user.username.clone()),
+        user: config.user.username.clone(),
+        door: door.map(str::to_string),
+        node,
+        detail,
+    };
+
+    if let Err(err) = append(config, &event) {
+        warn!("Couldn't record {} in audit log: {:#}", event.event, err);
+    }
+}
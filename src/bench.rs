@@ -0,0 +1,218 @@
+use super::config::{self, Capability};
+use super::dos::{Codepage, Templates};
+use super::BenchIoArgs;
+use anyhow::{anyhow, Context, Result};
+use comfy_table::modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS};
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, Table};
+use fs4::FileExt;
+use serde_json::json;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+const DROPFILE_WARN: Duration = Duration::from_millis(50);
+const LOCK_WARN: Duration = Duration::from_millis(10);
+const THROUGHPUT_WARN: f64 = 10.0;
+const NETWORK_FILESYSTEMS: [&str; 6] = ["nfs", "nfs4", "cifs", "smb3", "fuse.sshfs", "9p"];
+
+struct BenchResult {
+    name: &'static str,
+    path: PathBuf,
+    filesystem: String,
+    dropfile: Duration,
+    lock: Duration,
+    write_mbps: f64,
+}
+
+fn filesystem_type(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or(path.to_path_buf());
+    let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fstype = fields.next()?;
+
+            path.starts_with(mount_point)
+                .then_some((mount_point.len(), fstype.to_string()))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map_or("unknown".to_string(), |(_, fstype)| fstype)
+}
+
+fn bench_dropfiles(dir: &Path, config: &config::Config, iterations: u32) -> Result<Duration> {
+    let templates = Templates::new(&config.template_dir(), Codepage::default());
+    let vars = json!({
+        "user": {
+            "uid": config.user.uid,
+            "username": config.user.username,
+            "display_name": config.user.display_name,
+        },
+        "node": 1,
+        "current_time": "00:00",
+    });
+
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        templates.write_dos("door.sys", dir, &vars)?;
+        fs::File::open(dir.join("DOOR.SYS"))?.sync_all()?;
+    }
+
+    Ok(start.elapsed() / iterations)
+}
+
+fn bench_locks(dir: &Path, iterations: u32) -> Result<Duration> {
+    let path = dir.join("bench.lock");
+    let start = Instant::now();
+
+    for _ in 0..iterations {
+        let lockfile = fs::File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        lockfile.try_lock_exclusive()?;
+        lockfile.unlock()?;
+    }
+
+    Ok(start.elapsed() / iterations)
+}
+
+/// Write speed only: reading back a file we just wrote would only measure the page cache
+fn bench_throughput(dir: &Path, megabytes: usize) -> Result<f64> {
+    let path = dir.join("bench.dat");
+    let chunk = vec![0x55u8; 64 * 1024];
+    let chunks = megabytes * 16;
+
+    let start = Instant::now();
+    let mut file = fs::File::create(&path)?;
+
+    for _ in 0..chunks {
+        file.write_all(&chunk)?;
+    }
+
+    file.sync_all()?;
+
+    Ok(megabytes as f64 / start.elapsed().as_secs_f64())
+}
+
+fn bench_dir(
+    name: &'static str,
+    path: &Path,
+    args: &BenchIoArgs,
+    config: &config::Config,
+) -> Result<BenchResult> {
+    let dir = path.join(format!(".doorman-bench-{}", process::id()));
+
+    fs::create_dir_all(&dir).with_context(|| format!("Couldn't create {}", dir.display()))?;
+
+    let result = (|| -> Result<BenchResult> {
+        let dropfile = bench_dropfiles(&dir, config, args.iterations)?;
+        let lock = bench_locks(&dir, args.iterations)?;
+        let write_mbps = bench_throughput(&dir, args.megabytes)?;
+
+        Ok(BenchResult {
+            name,
+            path: path.to_path_buf(),
+            filesystem: filesystem_type(path),
+            dropfile,
+            lock,
+            write_mbps,
+        })
+    })();
+
+    fs::remove_dir_all(&dir).with_context(|| format!("Couldn't clean up {}", dir.display()))?;
+
+    result.with_context(|| format!("While benchmarking {}", path.display()))
+}
+
+fn warnings(result: &BenchResult) -> Vec<String> {
+    let mut warnings = vec![];
+
+    if NETWORK_FILESYSTEMS.contains(&result.filesystem.as_str()) {
+        warnings.push(format!(
+            "{} is on a network filesystem ({})",
+            result.name, result.filesystem
+        ));
+    }
+
+    if result.dropfile > DROPFILE_WARN {
+        warnings.push(format!(
+            "Writing drop files to {} is slow ({:.1?} each)",
+            result.name, result.dropfile
+        ));
+    }
+
+    if result.lock > LOCK_WARN {
+        warnings.push(format!(
+            "Taking locks in {} is slow ({:.1?} each)",
+            result.name, result.lock
+        ));
+    }
+
+    if result.write_mbps < THROUGHPUT_WARN {
+        warnings.push(format!(
+            "{} is slow to write to ({:.1} MB/s)",
+            result.name, result.write_mbps
+        ));
+    }
+
+    warnings
+}
+
+pub fn bench_io_command(args: &BenchIoArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Nightly) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let results = [
+        bench_dir("rundir", &config.rundir, args, config)?,
+        bench_dir("datadir", &config.datadir, args, config)?,
+    ];
+
+    let mut table = Table::new();
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            "Directory",
+            "Filesystem",
+            "Drop file",
+            "Lock",
+            "Write",
+        ]);
+
+    for result in results.iter() {
+        table.add_row(vec![
+            Cell::new(format!("{} ({})", result.name, result.path.display())),
+            Cell::new(&result.filesystem),
+            Cell::new(format!("{:.1?}", result.dropfile)),
+            Cell::new(format!("{:.1?}", result.lock)),
+            Cell::new(format!("{:.1} MB/s", result.write_mbps)),
+        ]);
+    }
+
+    println!("{table}");
+
+    let warnings: Vec<String> = results.iter().flat_map(warnings).collect();
+
+    if warnings.is_empty() {
+        println!("Storage looks fine for running doors.");
+    }
+
+    for warning in warnings {
+        println!("WARNING: {}", warning);
+    }
+
+    Ok(())
+}
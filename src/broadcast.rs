@@ -0,0 +1,68 @@
+use super::config::{self, Capability};
+use super::who::{self, WhoNode};
+use super::BroadcastArgs;
+use anyhow::{anyhow, Result};
+use log::warn;
+
+/// Writes message to every terminal open in container_id; launch.sh runs on one of
+/// them, so this lands on the player's screen
+pub fn send(config: &config::Config, container_id: &str, message: &str) -> Result<()> {
+    let mut exec = config.container_command("exec");
+    let status = exec
+        .arg(container_id)
+        .arg("sh")
+        .arg("-c")
+        .arg(r#"for tty in /dev/pts/[0-9]*; do printf '%s' "$1" > "$tty"; done"#)
+        .arg("sh")
+        .arg(message)
+        .status()?;
+
+    if !status.success() {
+        return Err(anyhow!(
+            "'{} exec {}' exited with status {}",
+            exec.get_program().to_string_lossy(),
+            container_id,
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn broadcast_command(args: &BroadcastArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Kick) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let door_name = match &args.door {
+        Some(door) => Some(config.get_door(door)?.name),
+        None => None,
+    };
+
+    let sessions: Vec<WhoNode> = who::who(&door_name, config)?
+        .into_iter()
+        .filter(|session| session.node.is_some())
+        .collect();
+
+    if sessions.is_empty() {
+        println!("Nobody is playing anything right now.");
+        return Ok(());
+    }
+
+    let message = format!("\r\n\x07*** {} ***\r\n", args.message.join(" "));
+    let mut sent = 0;
+
+    for session in sessions.iter() {
+        match send(config, &session.container_id, &message) {
+            Ok(()) => sent += 1,
+            Err(err) => warn!(
+                "Couldn't send to {} on {}: {:#}",
+                session.user, session.door, err
+            ),
+        }
+    }
+
+    println!("Sent to {} of {} sessions.", sent, sessions.len());
+
+    Ok(())
+}
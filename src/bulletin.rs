@@ -0,0 +1,94 @@
+use super::config::{self, Capability};
+use super::dos::{Codepage, Templates};
+use super::report;
+use anyhow::{anyhow, Context, Result};
+use chrono::{Datelike, Local, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// How many doors make the bulletin
+const TOP_DOORS: usize = 10;
+
+#[derive(Serialize, Debug)]
+struct TopDoor {
+    rank: usize,
+    name: String,
+    sessions: u64,
+    minutes: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct BulletinVars {
+    month: String,
+    doors: Vec<TopDoor>,
+}
+
+/// Renders the top doors bulletin into bulletin_dir, if there is one
+pub fn write(config: &config::Config) -> Result<Option<PathBuf>> {
+    let Some(bulletin_dir) = &config.bulletin_dir else {
+        return Ok(None);
+    };
+
+    let now = Local::now();
+    let month_start = now
+        .date_naive()
+        .with_day(1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .and_then(|datetime| datetime.and_local_timezone(Local).earliest())
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .ok_or(anyhow!("Couldn't work out when this month started"))?;
+
+    let entries: Vec<_> = report::sessions(config)?
+        .into_iter()
+        .filter(|entry| entry.start >= month_start)
+        .collect();
+
+    let doors = report::report(&entries)
+        .doors
+        .into_iter()
+        .take(TOP_DOORS)
+        .enumerate()
+        .map(|(index, total)| TopDoor {
+            rank: index + 1,
+            name: total.name,
+            sessions: total.sessions,
+            minutes: total.minutes,
+        })
+        .collect();
+
+    let vars = BulletinVars {
+        month: now.format("%B %Y").to_string(),
+        doors,
+    };
+
+    fs::create_dir_all(bulletin_dir).with_context(|| {
+        format!(
+            "Couldn't create bulletin directory {}",
+            bulletin_dir.display()
+        )
+    })?;
+
+    Templates::new(&config.template_dir(), Codepage::Cp437)
+        .write_dos("topdoors.ans", bulletin_dir, &vars)
+        .context("Couldn't write the top doors bulletin")?;
+
+    Ok(Some(bulletin_dir.join("TOPDOORS.ANS")))
+}
+
+pub fn bulletin_command(config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Stats) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    match write(config)? {
+        Some(path) => println!("Wrote {}", path.display()),
+        None => {
+            return Err(anyhow!(
+                "No bulletin directory configured; set bulletin_dir under doorman"
+            ))
+        }
+    }
+
+    Ok(())
+}
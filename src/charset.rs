@@ -0,0 +1,54 @@
+use super::dos::Codepage;
+use super::Charset;
+use anyhow::Result;
+use clap::ValueEnum;
+use comfy_table::modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS};
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::Table;
+use std::env;
+use std::io::{self, Write};
+
+/// comfy-table borders using only box drawing characters that CP437 has
+const CP437_BORDERS: &str = "││──╞═╪╡│─┼├┤┬┴┌┐└┘";
+
+/// The caller's character set: the one given on the command line,
+/// otherwise $DOORMAN_CHARSET, otherwise UTF-8
+pub fn detect(charset: Option<Charset>) -> Charset {
+    charset
+        .or_else(|| {
+            env::var("DOORMAN_CHARSET")
+                .ok()
+                .and_then(|value| Charset::from_str(&value, true).ok())
+        })
+        .unwrap_or(Charset::Utf8)
+}
+
+/// A table with borders the caller's terminal can draw
+pub fn new_table(charset: Charset) -> Table {
+    let mut table = Table::new();
+
+    match charset {
+        Charset::Utf8 => table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .apply_modifier(UTF8_SOLID_INNER_BORDERS),
+        Charset::Cp437 => table.load_preset(CP437_BORDERS),
+    };
+
+    table
+}
+
+/// Prints a line of text, encoded for the caller's terminal
+pub fn print_line(charset: Charset, text: &str) -> Result<()> {
+    match charset {
+        Charset::Utf8 => println!("{}", text),
+        Charset::Cp437 => {
+            let mut stdout = io::stdout().lock();
+
+            stdout.write_all(&Codepage::Cp437.encode(text))?;
+            stdout.write_all(b"\n")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use super::config::{self, Capability};
+use super::VerifyArgs;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// SHA-256 sums of a door's checksum_files, relative to door_path
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Manifest {
+    files: BTreeMap<PathBuf, String>,
+}
+
+fn manifest_path(config: &config::Config, door: &config::Door) -> PathBuf {
+    config
+        .datadir
+        .join("checksums")
+        .join(format!("{}.json", door.name))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file =
+        fs::File::open(path).with_context(|| format!("Couldn't open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 65536];
+
+    loop {
+        let count = file
+            .read(&mut buffer)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+
+        if count == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..count]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Records the current checksums of door's checksum_files
+pub fn record(config: &config::Config, door: &config::Door) -> Result<usize> {
+    let mut manifest = Manifest::default();
+
+    for file in door.options.checksum_files.iter() {
+        let sum = sha256_file(&door.options.door_path.join(file))?;
+        manifest.files.insert(file.clone(), sum);
+    }
+
+    let path = manifest_path(config, door);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Couldn't create checksums dir {}", parent.display()))?;
+    }
+
+    fs::write(&path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Couldn't write checksums to {}", path.display()))?;
+
+    Ok(manifest.files.len())
+}
+
+/// Compares door's checksum_files against the recorded checksums,
+/// returning a description of each one that doesn't match
+pub fn verify(config: &config::Config, door: &config::Door) -> Result<Vec<String>> {
+    let path = manifest_path(config, door);
+
+    if !path.exists() {
+        return Ok(vec![format!(
+            "no checksums recorded yet; run 'doorman verify --update {}'",
+            door.name
+        )]);
+    }
+
+    let manifest: Manifest = serde_json::from_str(
+        &fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read checksums from {}", path.display()))?,
+    )
+    .with_context(|| format!("Couldn't parse checksums in {}", path.display()))?;
+
+    let mut problems = vec![];
+
+    for file in door.options.checksum_files.iter() {
+        let full_path = door.options.door_path.join(file);
+
+        if !full_path.exists() {
+            problems.push(format!("{} is missing", file.display()));
+            continue;
+        }
+
+        match manifest.files.get(file) {
+            None => problems.push(format!("{} has no recorded checksum", file.display())),
+            Some(recorded) if *recorded != sha256_file(&full_path)? => {
+                problems.push(format!("{} has been modified", file.display()))
+            }
+            Some(_) => (),
+        }
+    }
+
+    Ok(problems)
+}
+
+pub fn verify_command(args: &VerifyArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Nightly) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let names = match &args.door {
+        Some(name) => vec![name.clone()],
+        None => config.door_names(),
+    };
+
+    let mut failed = false;
+
+    for name in names {
+        let door = config.get_door(&name)?;
+
+        if door.options.checksum_files.is_empty() {
+            if args.door.is_some() {
+                return Err(anyhow!("{} doesn't have any checksum_files", door.name));
+            }
+
+            continue;
+        }
+
+        if args.update {
+            let count = record(config, &door)?;
+            println!("{}: recorded checksums for {} files", door.name, count);
+            continue;
+        }
+
+        let problems = verify(config, &door)?;
+
+        if problems.is_empty() {
+            println!("{}: ok", door.name);
+        }
+
+        for problem in problems {
+            println!("{}: {}", door.name, problem);
+            failed = true;
+        }
+    }
+
+    if failed {
+        return Err(anyhow!("Some door files don't match their checksums"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use super::config::{self, Capability};
+use super::door;
+use super::gc;
+use super::session;
+use super::who::{self, WhoNode};
+use super::CleanupArgs;
+use anyhow::{anyhow, Context, Result};
+use fs4::FileExt;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether a running container is using the rundir or lockfile named door.suffix
+fn in_use(live: &[WhoNode], door: &str, suffix: Option<&str>) -> bool {
+    live.iter()
+        .filter(|node| node.door == door)
+        .any(|node| match suffix {
+            None => true,
+            Some("sysop") => node.command.is_some(),
+            Some(suffix) => node.node.is_some_and(|node| node.to_string() == suffix),
+        })
+}
+
+/// Splits "lord.1" into ("lord", Some("1")), and "lord" into ("lord", None)
+fn split_name(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('.') {
+        Some((door, suffix))
+            if suffix == "sysop"
+                || (!suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit())) =>
+        {
+            (door, Some(suffix))
+        }
+        _ => (name, None),
+    }
+}
+
+/// Door locks of the configured doors nothing is using, held exclusively so no
+/// launch or sysop command can start on them while we clean up after them
+fn lock_idle_doors(config: &config::Config) -> Result<HashMap<String, fs::File>> {
+    let mut held = HashMap::new();
+
+    for door_name in config.door_names() {
+        let lockfile = door::make_lockfile(&config.rundir.join(format!("{}.lock", door_name)))?;
+
+        if lockfile.try_lock_exclusive().is_err() {
+            debug!("{} is in use, leaving its files alone", door_name);
+            continue;
+        }
+
+        held.insert(door_name, lockfile);
+    }
+
+    Ok(held)
+}
+
+/// Whether the rundir entry called name belongs to a door we can clean up after;
+/// one whose door lock we're holding, or one that isn't configured any more
+fn idle(config: &config::Config, held: &HashMap<String, fs::File>, name: &str) -> bool {
+    config
+        .door_names()
+        .into_iter()
+        .filter(|door| name == door || name.starts_with(&format!("{}.", door)))
+        .max_by_key(String::len)
+        .is_none_or(|door| held.contains_key(&door))
+}
+
+/// Node and sysop rundirs no running container is using
+fn stale_rundirs(
+    config: &config::Config,
+    held: &HashMap<String, fs::File>,
+    live: &[WhoNode],
+) -> Result<Vec<PathBuf>> {
+    let mut rundirs: Vec<PathBuf> = vec![];
+
+    for entry in fs::read_dir(&config.rundir)? {
+        let entry = entry?;
+
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        let (door_name, suffix) = split_name(&name);
+
+        if suffix.is_none() || !idle(config, held, &name) || in_use(live, door_name, suffix) {
+            continue;
+        }
+
+        if let Some(Ok(node)) = suffix.map(str::parse::<i8>) {
+            if door::node_busy(config, door_name, node)? {
+                continue;
+            }
+        }
+
+        rundirs.push(entry.path());
+    }
+
+    rundirs.sort();
+
+    Ok(rundirs)
+}
+
+/// Lockfiles nobody is holding, for doors no running container is using
+fn stale_lockfiles(
+    config: &config::Config,
+    held: &HashMap<String, fs::File>,
+    live: &[WhoNode],
+) -> Result<Vec<PathBuf>> {
+    let mut lockfiles: Vec<PathBuf> = vec![];
+
+    for entry in fs::read_dir(&config.rundir)? {
+        let path = entry?.path();
+
+        if path.extension().is_none_or(|extension| extension != "lock") || !path.is_file() {
+            continue;
+        }
+
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let (door_name, suffix) = split_name(&stem);
+
+        // Door locks stay put; somebody waiting on one would otherwise end up
+        // holding a lock nobody else can see
+        if config.get_door(&stem).is_ok() {
+            continue;
+        }
+
+        if !idle(config, held, &stem) || in_use(live, door_name, suffix) {
+            continue;
+        }
+
+        let lockfile = door::make_lockfile(&path)?;
+
+        if lockfile.try_lock_exclusive().is_err() {
+            debug!("{} is still held", path.display());
+            continue;
+        }
+
+        lockfile.unlock()?;
+        lockfiles.push(path);
+    }
+
+    lockfiles.sort();
+
+    Ok(lockfiles)
+}
+
+/// Session files whose doorman process is gone, for nodes no running container is using
+fn stale_session_files(config: &config::Config, live: &[WhoNode]) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = session::session_files(config, &None)?
+        .into_iter()
+        .filter(|(_, state)| {
+            !state.running() && !in_use(live, &state.door, Some(&state.node.to_string()))
+        })
+        .map(|(path, _)| path)
+        .collect();
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Removes a lockfile, unless somebody took it since we looked
+fn remove_lockfile(path: &Path) -> Result<()> {
+    let lockfile = door::make_lockfile(path)?;
+
+    if lockfile.try_lock_exclusive().is_err() {
+        warn!("{} was taken again, leaving it alone", path.display());
+        return Ok(());
+    }
+
+    fs::remove_file(path).with_context(|| format!("Couldn't remove {}", path.display()))
+}
+
+pub fn cleanup_command(args: &CleanupArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Nightly) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let containers = gc::exited_containers(config)?;
+    let live = who::who(&None, config)?;
+    let held = lock_idle_doors(config)?;
+    let rundirs = stale_rundirs(config, &held, &live)?;
+    let lockfiles = stale_lockfiles(config, &held, &live)?;
+    let session_files = stale_session_files(config, &live)?;
+
+    if containers.is_empty()
+        && rundirs.is_empty()
+        && lockfiles.is_empty()
+        && session_files.is_empty()
+    {
+        println!("Nothing to clean up.");
+        return Ok(());
+    }
+
+    let verb = if args.dry_run {
+        "Would remove"
+    } else {
+        "Removing"
+    };
+
+    for container in containers.iter() {
+        println!(
+            "{} container {} ({} on {})",
+            verb, container.container_id, container.user, container.door
+        );
+    }
+
+    for rundir in rundirs.iter() {
+        println!("{} rundir {}", verb, rundir.display());
+    }
+
+    for lockfile in lockfiles.iter() {
+        println!("{} lockfile {}", verb, lockfile.display());
+    }
+
+    for session_file in session_files.iter() {
+        println!("{} session file {}", verb, session_file.display());
+    }
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    gc::remove_containers(config, &containers)?;
+
+    for rundir in rundirs.iter() {
+        if let Err(err) = fs::remove_dir_all(rundir) {
+            warn!("Couldn't remove {}: {}", rundir.display(), err);
+        }
+    }
+
+    for lockfile in lockfiles.iter() {
+        if let Err(err) = remove_lockfile(lockfile) {
+            warn!("{:#}", err);
+        }
+    }
+
+    for session_file in session_files.iter() {
+        if let Err(err) = fs::remove_file(session_file) {
+            warn!("Couldn't remove {}: {}", session_file.display(), err);
+        }
+    }
+
+    Ok(())
+}
@@ -1,18 +1,44 @@
+use super::audit;
 use super::container::ContainerEngine;
+use super::dos::{self, Codepage, DropFile};
+use super::guest::GuestOptions;
+use super::hooks::Hooks;
+use super::load::LaunchLimits;
+use super::pull::PullPolicy;
+use super::requires::Requirement;
+use super::signature::CosignOptions;
+use super::storage;
+use super::telemetry::OtlpOptions;
+use super::translate::Translation;
 use super::user;
+use super::webhook::WebhookOptions;
 use anyhow::anyhow;
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use log::{info, debug};
 use nix::unistd;
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{de, Deserialize, Deserializer};
+use serde_aux::serde_introspection::serde_introspect;
+use serde_yaml::{Mapping, Value};
 use std::collections::HashMap;
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::OnceLock;
 
 #[derive(Deserialize, Debug)]
 struct DoormanOptions {
+    /// Name of the BBS, for drop files
+    bbs_name: Option<String>,
+
+    /// Name of the sysop, for drop files
+    sysop_name: Option<String>,
+
+    /// Where the BBS is, for drop files
+    location: Option<String>,
+
     /// The location of doorman's persistent data
     datadir: Option<PathBuf>,
 
@@ -21,9 +47,88 @@ struct DoormanOptions {
 
     /// List of users that should be considered sysops
     sysops: Option<Vec<String>>,
+
+    /// Co-sysops and the capabilities granted to each of them
+    cosysops: Option<HashMap<String, Vec<Capability>>>,
+
+    /// Rules for display names before they're written to drop files
+    display_names: Option<user::DisplayNamePolicy>,
+
+    /// Environment variables to pass from the caller into every door's container;
+    /// a trailing * matches any variable starting with what comes before it
+    pass_env: Option<Vec<String>>,
+
+    /// Directory of extra files that each define one or more doors, relative to
+    /// the config file; defaults to doors.d
+    doors_dir: Option<PathBuf>,
+
+    /// Host load and memory limits above which new launches are refused
+    launch_limits: Option<LaunchLimits>,
+
+    /// OpenTelemetry collector to send traces of launches and maintenence to
+    otlp: Option<OtlpOptions>,
+
+    /// Minutes a session may last, for every door
+    time_limit: Option<u32>,
+
+    /// Minutes a session may last for particular users, by username
+    user_time_limits: Option<HashMap<String, u32>>,
+
+    /// Also record finished sessions in an SQLite database in datadir, for
+    /// 'doorman stats-db' queries; defaults to false
+    stats_db: Option<bool>,
+
+    /// Directory to write a monthly top doors bulletin into, for the BBS to show;
+    /// rewritten by 'doorman bulletin' and after nightly maintenance
+    bulletin_dir: Option<PathBuf>,
+
+    /// URLs to POST JSON to when people launch and exit doors, nightly maintenence
+    /// finishes, or something goes wrong
+    webhooks: Option<Vec<WebhookOptions>>,
+
+    /// Programs to run on the host before every door launches and after it exits
+    hooks: Option<Hooks>,
+
+    /// How many doors each user may launch a day, counting every door
+    daily_launches: Option<u32>,
+
+    /// Security levels to write to drop files, by username, @group for members of
+    /// a Unix group, or @sysops for doorman's sysops; a username wins over groups,
+    /// and the highest level wins between groups
+    security_levels: Option<HashMap<String, u16>>,
+
+    /// Security level for everyone security_levels doesn't cover; defaults to 100
+    default_security_level: Option<u16>,
+
+    /// A shared account for guests, who pick a handle when they arrive and are
+    /// kept to their own datadir, doors and time limit
+    guest: Option<GuestOptions>,
 }
 
-fn default_dosemu_image() -> String {
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    /// Stop, message, or snoop on other people's sessions
+    Kick,
+
+    /// Launch doors as another user
+    Impersonate,
+
+    /// Run a door's configuration program
+    Configure,
+
+    /// Run a door's nightly maintenence, and look after images, containers and
+    /// leftovers: verify, pull, gc, cleanup, bench-io and simulate
+    Nightly,
+
+    /// Lock doors for maintenance, turning new players away
+    Lock,
+
+    /// View statistics about other users
+    Stats,
+}
+
+pub fn default_dosemu_image() -> String {
     String::from("ghcr.io/jordemort/doorman-dosemu:main")
 }
 
@@ -38,23 +143,110 @@ struct ContainerOptions {
     #[serde(default = "default_dosemu_image")]
     /// Container image with dosemu; defaults to ghcr.io/jordemort/doorman-dosemu:main
     dosemu_image: String,
+
+    /// Health check command to run inside door containers, in addition to any
+    /// healthcheck the image defines itself
+    health_cmd: Option<String>,
+
+    #[serde(default)]
+    /// When to pull images: always (before every launch), missing, or never.
+    /// Defaults to missing.
+    pull_policy: PullPolicy,
+
+    #[serde(default)]
+    /// Refuse to run images that aren't pinned by digest, so a tag like :main
+    /// can't change underneath the board. Defaults to false.
+    require_digest: bool,
+
+    /// Check image signatures with cosign before running them
+    cosign: Option<CosignOptions>,
+}
+
+/// Caps on what a door's container can use, so a runaway door can't eat the host
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ResourceLimits {
+    /// Memory limit, i.e. 256m
+    pub memory: Option<String>,
+
+    /// Number of CPUs the container can use, i.e. 0.5
+    pub cpus: Option<f64>,
+
+    /// Maximum number of processes in the container
+    pub pids: Option<u32>,
 }
 
 fn default_max_nodes() -> i8 {
     1
 }
 
+fn default_first_node() -> i8 {
+    1
+}
+
+fn default_nightly_retry_delay() -> u64 {
+    60
+}
+
+/// A node number as a map key; TOML keys are always strings, YAML's needn't be
+#[derive(Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+enum NodeKey {
+    Number(i8),
+    Text(String),
+}
+
+fn deserialize_node_map<'de, D, T>(deserializer: D) -> Result<HashMap<i8, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    HashMap::<NodeKey, T>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, value)| match key {
+            NodeKey::Number(node) => Ok((node, value)),
+            NodeKey::Text(text) => text
+                .parse()
+                .map(|node| (node, value))
+                .map_err(|_| de::Error::custom(format!("'{}' isn't a node number", text))),
+        })
+        .collect()
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct DoorOptions {
+    /// Short description of the door, for menus and listings
+    pub description: Option<String>,
+
+    /// Category to group the door under, i.e. RPG, strategy, or casino
+    pub category: Option<String>,
+
+    #[serde(default)]
+    /// Extra keywords for finding the door
+    pub tags: Vec<String>,
+
     /// Path to door files; this will be mounted as drive Z: in DOSEMU
     pub door_path: PathBuf,
 
+    /// Container image to run this door in instead of container.dosemu_image,
+    /// i.e. one with a special TSR or a pinned dosemu version
+    pub image: Option<String>,
+
     #[serde(default = "default_max_nodes")]
     /// Number of concurrent players to allow.
     /// Make sure you have this many nodes configured in your door!
     /// Defaults to 1.
     pub max_nodes: i8,
 
+    #[serde(default = "default_first_node")]
+    /// Number of the door's first node, i.e. 3 if a legacy BBS still uses nodes 1
+    /// and 2; the door gets max_nodes nodes from here. Defaults to 1.
+    pub first_node: i8,
+
+    #[serde(default)]
+    /// Node numbers to use, i.e. [3, 5, 7], instead of max_nodes nodes starting
+    /// at first_node
+    pub nodes: Vec<i8>,
+
     /// DOS command to lauch the door.
     pub launch_commands: String,
 
@@ -63,6 +255,187 @@ pub struct DoorOptions {
 
     /// DOS commands to run the door's nightly maintenence.
     pub nightly_commands: Option<String>,
+
+    /// Door to launch when this one exits, or menu to go back to the door menu.
+    /// A door can also pick one itself by writing its name to NEXTDOOR.TXT.
+    pub next_door: Option<String>,
+
+    /// DOS commands to get the door ready for its first player, i.e. rebuilding indexes.
+    /// Run by the warmup command, and after nightly maintenence succeeds.
+    pub warmup_commands: Option<String>,
+
+    #[serde(default)]
+    /// Number of times to retry nightly maintenence if it fails. Defaults to 0.
+    pub nightly_retries: u32,
+
+    #[serde(default = "default_nightly_retry_delay")]
+    /// Seconds to wait between nightly maintenence retries. Defaults to 60.
+    pub nightly_retry_delay: u64,
+
+    #[serde(default)]
+    /// Don't translate the door's output from ANSI+CP437, as if every caller
+    /// passed --raw; callers can still ask for translation with --no-raw.
+    /// Defaults to false.
+    pub raw: bool,
+
+    #[serde(default)]
+    /// Glyph substitutions and escape sequences to strip when translating the
+    /// door's output from ANSI+CP437; ignored in raw mode
+    pub translate: Translation,
+
+    #[serde(default)]
+    /// Code page for drop files and batch files, i.e. cp850 or cp866.
+    /// Defaults to cp437.
+    pub codepage: Codepage,
+
+    #[serde(default)]
+    /// Squeeze file names written for the door into 8.3 characters instead of
+    /// refusing to write them. Defaults to false.
+    pub shorten_filenames: bool,
+
+    #[serde(default = "dos::default_dropfiles")]
+    /// Drop files to write for the door: any of door.sys, door32.sys, dorinfo1.def,
+    /// dorinfox.def (DORINFO1.DEF named after the node), chain.txt, or sfdoors.dat.
+    /// Defaults to door.sys, door32.sys, dorinfo1.def, and chain.txt.
+    pub dropfiles: Vec<DropFile>,
+
+    #[serde(default)]
+    /// Files in door_path that shouldn't change, i.e. executables and static game
+    /// data; nightly maintenence warns if they don't match the checksums recorded
+    /// by 'doorman verify --update'
+    pub checksum_files: Vec<PathBuf>,
+
+    /// File in door_path to write play statistics to during nightly maintenence,
+    /// i.e. DOORSTAT.TXT. No statistics file is written if this isn't set.
+    pub stats_file: Option<String>,
+
+    #[serde(default)]
+    /// Environment variables to pass from the caller into this door's container,
+    /// in addition to the ones in the doorman section
+    pub pass_env: Vec<String>,
+
+    /// TERM to use inside the container instead of the caller's, for terminal
+    /// backends that only behave with a particular one
+    pub term: Option<String>,
+
+    /// Compiled terminfo entry to mount into the container, i.e.
+    /// /usr/share/terminfo/x/xterm-256color; use with term if the image lacks it
+    pub terminfo: Option<PathBuf>,
+
+    #[serde(default)]
+    /// Extra host paths to mount into the container, i.e. a shared inter-BBS
+    /// directory; mount under /mnt/door to make them visible on drive Z:, and
+    /// add :ro to the container path to mount them read-only
+    pub extra_volumes: HashMap<PathBuf, PathBuf>,
+
+    /// Container network to attach the door to, i.e. bridge or a named network,
+    /// for doors that need to talk to inter-BBS leagues. Doors get no network
+    /// access if this isn't set.
+    pub network: Option<String>,
+
+    #[serde(default)]
+    /// Memory, CPU, and process limits for the door's containers
+    pub limits: ResourceLimits,
+
+    /// Size of a private scratch drive (X:) for each session, i.e. 64m, for doors
+    /// that write noisy temp files. TEMP and TMP point at it, and it's thrown
+    /// away when the session ends.
+    pub scratch: Option<String>,
+
+    /// Minutes a session may last. If doorman's time_limit or user_time_limits
+    /// also apply, the smallest limit wins.
+    pub time_limit: Option<u32>,
+
+    /// How many times each user may launch this door a day. If doorman's
+    /// daily_launches is also set, both have to allow the launch.
+    pub daily_launches: Option<u32>,
+
+    #[serde(default)]
+    /// Let players on different nodes page each other. Each node gets a P: drive
+    /// shared with the door's other nodes, with an inbox per node (P:\NODE1.TXT and
+    /// so on) that 'doorman page' and the door's batch commands can append to.
+    /// Defaults to false.
+    pub paging: bool,
+
+    #[serde(default)]
+    /// Things outside doorman the door needs before anyone can play it: paths
+    /// that have to exist, mount points, or TCP services like league servers
+    pub requires: Vec<Requirement>,
+
+    #[serde(default)]
+    /// Let players watch each other's games; sysops can always watch.
+    /// Defaults to false.
+    pub spectators: bool,
+
+    #[serde(default)]
+    /// Let one player use more than one of the door's nodes at a time; otherwise
+    /// they get offered their running session back. Defaults to false.
+    pub allow_multiple: bool,
+
+    #[serde(default)]
+    /// When every node is busy, put callers in line for the next free one instead
+    /// of turning them away, as if they all passed --wait. Defaults to false.
+    pub wait_for_node: bool,
+
+    #[serde(default, deserialize_with = "deserialize_node_map")]
+    /// Nodes kept free for particular people, by node number: a list of usernames,
+    /// @group for members of a Unix group, or @sysops for doorman's sysops. They
+    /// don't have to wait in line for a free node kept for them.
+    pub reserved_nodes: HashMap<i8, Vec<String>>,
+
+    #[serde(default)]
+    /// Only these users may play or configure the door, i.e. for adults-only or
+    /// beta doors, along with members of allow_groups; everyone may if both are
+    /// empty. Sysops always may.
+    pub allow_users: Vec<String>,
+
+    #[serde(default)]
+    /// Users who may not play or configure the door, even if they're allowed otherwise
+    pub deny_users: Vec<String>,
+
+    #[serde(default)]
+    /// Members of these Unix groups may play or configure the door, along with
+    /// allow_users, so access can be managed with ordinary account tools
+    pub allow_groups: Vec<String>,
+
+    #[serde(default)]
+    /// Members of these Unix groups may not play or configure the door, even if
+    /// they're allowed otherwise
+    pub deny_groups: Vec<String>,
+
+    #[serde(default)]
+    /// Programs to run on the host before this door launches and after it exits,
+    /// after the ones in the doorman section
+    pub hooks: Hooks,
+}
+
+impl DoorOptions {
+    /// The door's node numbers, from nodes or first_node and max_nodes
+    pub fn node_numbers(&self) -> Result<Vec<i8>> {
+        if !self.nodes.is_empty() {
+            return Ok(self.nodes.clone());
+        }
+
+        (0..self.max_nodes)
+            .map(|offset| {
+                self.first_node.checked_add(offset).ok_or(anyhow!(
+                    "first_node {} and max_nodes {} go past node {}",
+                    self.first_node,
+                    self.max_nodes,
+                    i8::MAX
+                ))
+            })
+            .collect()
+    }
+
+    /// Highest node number the door uses
+    pub fn last_node(&self) -> i8 {
+        self.node_numbers()
+            .unwrap_or_default()
+            .into_iter()
+            .max()
+            .unwrap_or(self.first_node)
+    }
 }
 
 pub struct Door {
@@ -78,64 +451,494 @@ struct ConfigFile {
     /// Options relating to how doorman runs containers
     container: Option<ContainerOptions>,
 
+    #[serde(default)]
     /// Door definitions
     doors: HashMap<String, DoorOptions>,
 }
 impl ConfigFile {
-    fn from_path(config_path: &PathBuf) -> Result<ConfigFile> {
-        let config_file = fs::File::open(&config_path)
-            .with_context(|| format!("Couldn't open config file: {}", config_path.display()))?;
+    fn parse(config_path: &Path, text: &str) -> Result<ConfigFile> {
+        parse_as(config_path, text)
+    }
 
-        let config: ConfigFile = serde_yaml::from_reader(config_file)
-            .with_context(|| format!("Couldn't parse config file: {}", config_path.display()))?;
+    /// Reads the config at config_path along with the doors directory, with any
+    /// overrides from the environment
+    fn from_path(config_path: &Path) -> Result<ConfigFile> {
+        let overrides = env_overrides();
 
-        Ok(config)
+        if overrides.is_empty() {
+            let mut config: ConfigFile = read_as(config_path)?;
+            let doors_dir = config
+                .doorman
+                .as_ref()
+                .and_then(|doorman| doorman.doors_dir.as_deref());
+
+            include_doors(&doors_dir_for(config_path, doors_dir), &mut config.doors)?;
+
+            return Ok(config);
+        }
+
+        let mut document: Value = read_as(config_path)?;
+
+        // Door overrides wait until the doors directory is merged in, so they can
+        // reach doors defined there too
+        let (door_overrides, overrides): (Vec<_>, Vec<_>) = overrides
+            .into_iter()
+            .partition(|(_, path, _)| path.len() > 1 && path[0] == "doors");
+
+        for (name, path, value) in &overrides {
+            debug!("Overriding {} from the environment", path.join("."));
+            set_key(&mut document, path, value.clone())
+                .with_context(|| format!("Couldn't apply {}", name))?;
+        }
+
+        let doors_dir = document
+            .get("doorman")
+            .and_then(|doorman| doorman.get("doors_dir"))
+            .and_then(Value::as_str)
+            .map(Path::new);
+        let doors_dir = doors_dir_for(config_path, doors_dir);
+
+        let doors_key = ["doors".to_string()];
+        let mut doors: HashMap<String, Value> = match document.get("doors") {
+            Some(Value::Null) | None => HashMap::new(),
+            Some(doors) => serde_yaml::from_value(doors.clone()).with_context(|| {
+                format!("Couldn't parse config file: {}", config_path.display())
+            })?,
+        };
+
+        include_doors(&doors_dir, &mut doors)?;
+        set_key(&mut document, &doors_key, serde_yaml::to_value(doors)?)?;
+
+        for (name, path, value) in door_overrides {
+            debug!("Overriding {} from the environment", path.join("."));
+            set_key(&mut document, &path, value)
+                .with_context(|| format!("Couldn't apply {}", name))?;
+        }
+
+        serde_yaml::from_value(document).with_context(|| {
+            format!(
+                "Couldn't apply environment overrides to config file: {}",
+                config_path.display()
+            )
+        })
     }
 }
 
+/// Config keys set in the environment, i.e. DOORMAN_CONTAINER__DOSEMU_IMAGE for
+/// container.dosemu_image. Values are parsed as YAML, so numbers and lists work.
+fn env_overrides() -> Vec<(String, Vec<String>, Value)> {
+    let mut overrides: Vec<(String, Vec<String>, Value)> = env::vars()
+        .filter_map(|(name, value)| {
+            let key = name.strip_prefix("DOORMAN_")?;
+
+            // Variables like DOORMAN_CONFIG aren't config keys
+            if !key.contains("__") {
+                return None;
+            }
+
+            let path = key.split("__").map(str::to_lowercase).collect();
+            let value = serde_yaml::from_str(&value).unwrap_or(Value::String(value));
+
+            Some((name, path, value))
+        })
+        .collect();
+
+    overrides.sort_by(|a, b| a.1.cmp(&b.1));
+    overrides
+}
+
+/// Sets the key at path to value, adding sections along the way; lists and other
+/// values can't be reached into
+fn set_key(document: &mut Value, path: &[String], value: Value) -> Result<()> {
+    let mut section = document;
+
+    for (depth, key) in path.iter().enumerate() {
+        if section.is_null() {
+            *section = Value::Mapping(Mapping::new());
+        }
+
+        let Some(mapping) = section.as_mapping_mut() else {
+            let parent = match depth {
+                0 => "The config file".to_string(),
+                _ => format!("'{}'", path[..depth].join(".")),
+            };
+
+            return Err(anyhow!(
+                "{} isn't a section, so it can't have '{}' set in it",
+                parent,
+                key
+            ));
+        };
+
+        // Variable names are upper case, but door names in the config needn't be
+        let key = mapping
+            .keys()
+            .find(|existing| {
+                existing
+                    .as_str()
+                    .is_some_and(|existing| existing.eq_ignore_ascii_case(key))
+            })
+            .cloned()
+            .unwrap_or_else(|| Value::String(key.clone()));
+
+        section = mapping.entry(key).or_insert(Value::Null);
+    }
+
+    *section = value;
+
+    Ok(())
+}
+
+/// Doors defined in a file in the doors directory
+type DoorsFile = HashMap<String, DoorOptions>;
+
+fn parse_as<T: DeserializeOwned>(path: &Path, text: &str) -> Result<T> {
+    if is_toml(path) {
+        Ok(toml::from_str(text)?)
+    } else {
+        Ok(serde_yaml::from_str(text)?)
+    }
+}
+
+fn read_as<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Couldn't open config file: {}", path.display()))?;
+
+    parse_as(path, &text).with_context(|| format!("Couldn't parse config file: {}", path.display()))
+}
+
+fn doors_dir_for(config_path: &Path, doors_dir: Option<&Path>) -> PathBuf {
+    let config_dir = config_path.parent().unwrap_or(Path::new("."));
+
+    match doors_dir {
+        Some(doors_dir) => config_dir.join(doors_dir),
+        None => config_dir.join("doors.d"),
+    }
+}
+
+/// The directory of extra door files for the config in use
+pub fn doors_dir() -> Result<PathBuf> {
+    let config_path = config_path();
+    let config = ConfigFile::from_path(&config_path)?;
+    let doors_dir = config.doorman.and_then(|doorman| doorman.doors_dir);
+
+    Ok(doors_dir_for(&config_path, doors_dir.as_deref()))
+}
+
+/// Door files in doors_dir, in the order they're loaded
+pub fn door_files(doors_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !doors_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(doors_dir)
+        .with_context(|| format!("Couldn't read {}", doors_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "yml" || ext == "yaml" || ext == "toml")
+        })
+        .collect();
+
+    files.sort();
+
+    Ok(files)
+}
+
+/// Adds the doors from the doors directory to the ones in the main config
+fn include_doors<T: DeserializeOwned>(
+    doors_dir: &Path,
+    doors: &mut HashMap<String, T>,
+) -> Result<()> {
+    for path in door_files(doors_dir)? {
+        let included: HashMap<String, T> = read_as(&path)?;
+
+        for (name, options) in included {
+            if doors.contains_key(&name) {
+                return Err(anyhow!(
+                    "Door '{}' in {} is already defined somewhere else",
+                    name,
+                    path.display()
+                ));
+            }
+
+            doors.insert(name, options);
+        }
+    }
+
+    Ok(())
+}
+
+/// Which file in the doors directory defines a door, if any
+pub fn included_door_file(name: &str) -> Result<Option<PathBuf>> {
+    for path in door_files(&doors_dir()?)? {
+        let included: DoorsFile = read_as(&path)?;
+
+        if included.contains_key(name) {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Whether the config at path is TOML rather than YAML
+pub fn is_toml(config_path: &Path) -> bool {
+    config_path.extension().is_some_and(|ext| ext == "toml")
+}
+
+/// Checks that text would parse as the config file at config_path
+pub fn validate(config_path: &Path, text: &str) -> Result<()> {
+    ConfigFile::parse(config_path, text)?;
+    Ok(())
+}
+
+/// A key in the config file that doorman would silently ignore
+pub struct UnknownKey {
+    pub path: String,
+    pub suggestion: Option<&'static str>,
+}
+
+fn path_segments(path: &serde_ignored::Path, segments: &mut Vec<String>) {
+    match path {
+        serde_ignored::Path::Root => (),
+        serde_ignored::Path::Seq { parent, index } => {
+            path_segments(parent, segments);
+            segments.push(index.to_string());
+        }
+        serde_ignored::Path::Map { parent, key } => {
+            path_segments(parent, segments);
+            segments.push(key.clone());
+        }
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => path_segments(parent, segments),
+    }
+}
+
+/// Keys that are allowed in the section at path
+fn known_fields(path: &[String]) -> &'static [&'static str] {
+    let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+    match path.as_slice() {
+        [] => serde_introspect::<ConfigFile>(),
+        ["doorman"] => serde_introspect::<DoormanOptions>(),
+        ["doorman", "display_names"] => serde_introspect::<user::DisplayNamePolicy>(),
+        ["doorman", "launch_limits"] => serde_introspect::<LaunchLimits>(),
+        ["doorman", "otlp"] => serde_introspect::<OtlpOptions>(),
+        ["doorman", "webhooks", _] => serde_introspect::<WebhookOptions>(),
+        ["doorman", "hooks"] => serde_introspect::<Hooks>(),
+        ["doorman", "guest"] => serde_introspect::<GuestOptions>(),
+        ["container"] => serde_introspect::<ContainerOptions>(),
+        ["container", "cosign"] => serde_introspect::<CosignOptions>(),
+        ["doors", _] => serde_introspect::<DoorOptions>(),
+        ["doors", _, "limits"] => serde_introspect::<ResourceLimits>(),
+        ["doors", _, "hooks"] => serde_introspect::<Hooks>(),
+        ["doors", _, "translate"] => serde_introspect::<Translation>(),
+        ["doors", _, "requires", _] => serde_introspect::<Requirement>(),
+        _ => &[],
+    }
+}
+
+fn ignored_keys<T: DeserializeOwned>(
+    path: &Path,
+    text: &str,
+    prefix: &[&str],
+) -> Result<Vec<UnknownKey>> {
+    let mut unknown = vec![];
+
+    let callback = |path: serde_ignored::Path| {
+        let mut segments: Vec<String> = prefix.iter().map(|segment| segment.to_string()).collect();
+        path_segments(&path, &mut segments);
+
+        let full_path = segments.join(".");
+        let key = segments.pop().unwrap_or_default();
+        let suggestion = known_fields(&segments)
+            .iter()
+            .map(|field| (strsim::levenshtein(&key, field), *field))
+            .filter(|(distance, _)| *distance <= 3)
+            .min()
+            .map(|(_, field)| field);
+
+        unknown.push(UnknownKey {
+            path: full_path,
+            suggestion,
+        });
+    };
+
+    let _: T = if is_toml(path) {
+        serde_ignored::deserialize(toml::Deserializer::new(text), callback)?
+    } else {
+        serde_ignored::deserialize(serde_yaml::Deserializer::from_str(text), callback)?
+    };
+
+    Ok(unknown)
+}
+
+/// Parses text as a config file, returning the keys that don't mean anything to doorman
+pub fn unknown_keys(config_path: &Path, text: &str) -> Result<Vec<UnknownKey>> {
+    ignored_keys::<ConfigFile>(config_path, text, &[])
+}
+
+/// Like unknown_keys, but for a file in the doors directory
+pub fn unknown_door_keys(path: &Path, text: &str) -> Result<Vec<UnknownKey>> {
+    ignored_keys::<DoorsFile>(path, text, &["doors"])
+}
+
 pub struct Config {
     pub datadir: PathBuf,
     pub rundir: PathBuf,
     pub user: user::User,
+    pub impersonated_by: Option<String>,
+    pub bbs_name: Option<String>,
+    pub sysop_name: Option<String>,
+    pub location: Option<String>,
     pub dosemu_image: String,
+    pub display_names: user::DisplayNamePolicy,
+    pub pass_env: Vec<String>,
+    pub launch_limits: LaunchLimits,
+    pub otlp: Option<OtlpOptions>,
+    pub time_limit: Option<u32>,
+    pub user_time_limits: HashMap<String, u32>,
+    pub stats_db: bool,
+    pub bulletin_dir: Option<PathBuf>,
+    pub webhooks: Vec<WebhookOptions>,
+    pub hooks: Hooks,
+    pub daily_launches: Option<u32>,
+    pub security_levels: HashMap<String, u16>,
+    pub default_security_level: u16,
+
+    /// Set when the caller came in through the guest account
+    pub guest: Option<GuestOptions>,
+
+    /// Where templates are overridden; guests use the main datadir's
+    pub templates: PathBuf,
+    pub pull_policy: PullPolicy,
+    pub require_digest: bool,
+    pub cosign: Option<CosignOptions>,
 
     uid: unistd::Uid,
     gid: unistd::Gid,
     sysops: Vec<String>,
+    cosysops: HashMap<String, Vec<Capability>>,
     doors: HashMap<String, DoorOptions>,
     engine: ContainerEngine,
+    health_cmd: Option<String>,
 }
+
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+fn project_dirs() -> ProjectDirs {
+    ProjectDirs::from("dev", "jordemort", "doorman").unwrap()
+}
+
+/// Use path as the config file instead of looking in the usual places
+pub fn set_config_path(path: PathBuf) {
+    CONFIG_PATH.set(path).ok();
+}
+
+/// Whether the caller gets to pick the config file. Through sudo or doas, a config
+/// of their own could make them a sysop or pick the container engine, so only
+/// sysops of the usual config may.
+pub fn may_choose_config() -> bool {
+    if env::var_os("SUDO_USER").is_none() && env::var_os("DOAS_USER").is_none() {
+        return true;
+    }
+
+    Config::load().is_ok_and(|config| config.is_sysop())
+}
+
+/// Where new configs are written
+pub fn default_config_path() -> PathBuf {
+    project_dirs().config_dir().join("doorman.yml")
+}
+
+/// The config file in use: the one given with --config, otherwise doorman.yml,
+/// or doorman.toml if there's no YAML one
+pub fn config_path() -> PathBuf {
+    if let Some(path) = CONFIG_PATH.get() {
+        return path.clone();
+    }
+
+    let toml_path = project_dirs().config_dir().join("doorman.toml");
+    let yaml_path = default_config_path();
+
+    if !yaml_path.exists() && toml_path.exists() {
+        toml_path
+    } else {
+        yaml_path
+    }
+}
+
+pub fn default_datadir() -> PathBuf {
+    PathBuf::from(project_dirs().data_dir())
+}
+
+pub fn default_rundir(datadir: &Path) -> PathBuf {
+    project_dirs()
+        .runtime_dir()
+        .map_or(datadir.join("run"), PathBuf::from)
+}
+
 impl Config {
     pub fn load() -> Result<Config> {
         let user = user::User::calling_user()?;
 
         info!("Running as user '{}' with UID {}", user.username, user.uid);
 
-        let project_dirs = ProjectDirs::from("dev", "jordemort", "doorman").unwrap();
-        let config_path = project_dirs.config_dir().join("doorman.yml");
+        let config_path = config_path();
         let config = ConfigFile::from_path(&config_path)?;
+        let doors = config.doors;
 
-        let doorman = config.doorman.unwrap_or_else(|| DoormanOptions {
+        let doorman = config.doorman.unwrap_or(DoormanOptions {
+            bbs_name: None,
+            sysop_name: None,
+            location: None,
             datadir: None,
             rundir: None,
             sysops: None,
+            cosysops: None,
+            display_names: None,
+            pass_env: None,
+            doors_dir: None,
+            launch_limits: None,
+            otlp: None,
+            time_limit: None,
+            user_time_limits: None,
+            stats_db: None,
+            bulletin_dir: None,
+            webhooks: None,
+            hooks: None,
+            daily_launches: None,
+            security_levels: None,
+            default_security_level: None,
+            guest: None,
         });
 
-        let datadir = doorman
-            .datadir
-            .unwrap_or(PathBuf::from(project_dirs.data_dir()));
+        let datadir = doorman.datadir.unwrap_or_else(default_datadir);
+        let templates = datadir.join("templates");
+
+        // Guests share the rundir with everybody else, so they take the same nodes
+        // and locks
+        let rundir = doorman.rundir.unwrap_or_else(|| default_rundir(&datadir));
+        let guest = doorman.guest.filter(|guest| guest.account == user.username);
+
+        let datadir = match &guest {
+            Some(guest) => {
+                info!("'{}' is the guest account", user.username);
+                guest
+                    .datadir
+                    .clone()
+                    .unwrap_or_else(|| datadir.join("guest"))
+            }
+            None => datadir,
+        };
 
         if !datadir.exists() {
             fs::create_dir_all(&datadir)
                 .with_context(|| format!("Couldn't create datadir: {}", datadir.display()))?;
         }
 
-        let rundir = doorman.rundir.unwrap_or(
-            project_dirs
-                .runtime_dir()
-                .map_or(datadir.join("run"), |rundir| PathBuf::from(rundir)),
-        );
-
         if !rundir.exists() {
             fs::create_dir_all(&rundir)
                 .with_context(|| format!("Couldn't create rundir: {}", rundir.display()))?;
@@ -145,6 +948,10 @@ impl Config {
             engine_path: None,
             rootless_podman: None,
             dosemu_image: default_dosemu_image(),
+            health_cmd: None,
+            pull_policy: PullPolicy::default(),
+            require_digest: false,
+            cosign: None,
         });
 
         let engine = ContainerEngine::new(&container.engine_path, &container.rootless_podman)?;
@@ -153,15 +960,45 @@ impl Config {
             datadir,
             rundir,
             user,
+            impersonated_by: None,
+            bbs_name: doorman.bbs_name,
+            sysop_name: doorman.sysop_name,
+            location: doorman.location,
             dosemu_image: container.dosemu_image,
+            display_names: doorman.display_names.unwrap_or_default(),
+            pass_env: doorman.pass_env.unwrap_or_default(),
+            launch_limits: doorman.launch_limits.unwrap_or_default(),
+            time_limit: doorman.time_limit,
+            user_time_limits: doorman.user_time_limits.unwrap_or_default(),
+            stats_db: doorman.stats_db.unwrap_or(false),
+            bulletin_dir: doorman.bulletin_dir,
+            webhooks: doorman.webhooks.unwrap_or_default(),
+            hooks: doorman.hooks.unwrap_or_default(),
+            daily_launches: doorman.daily_launches,
+            security_levels: doorman.security_levels.unwrap_or_default(),
+            default_security_level: doorman.default_security_level.unwrap_or(100),
+            guest,
+            templates,
+            otlp: doorman.otlp,
+            pull_policy: container.pull_policy,
+            require_digest: container.require_digest,
+            cosign: container.cosign,
             uid: unistd::getuid(),
             gid: unistd::getgid(),
             sysops: doorman.sysops.unwrap_or(vec![]),
-            doors: config.doors,
+            cosysops: doorman.cosysops.unwrap_or_default(),
+            doors,
             engine,
+            health_cmd: container.health_cmd,
         })
     }
 
+    /// Checks that rundir and datadir are writable and not full
+    pub fn check_storage(&self) -> Result<()> {
+        storage::check_writable(&self.rundir, "rundir")?;
+        storage::check_writable(&self.datadir, "datadir")
+    }
+
     pub fn get_door(&self, name: &str) -> Result<Door> {
         let options = self
             .doors
@@ -174,21 +1011,163 @@ impl Config {
         })
     }
 
+    pub fn template_dir(&self) -> PathBuf {
+        self.templates.clone()
+    }
+
+    pub fn door_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.doors.keys().cloned().collect();
+
+        names.sort();
+        names
+    }
+
+    /// Names of the doors the user is allowed to play
+    pub fn playable_door_names(&self) -> Vec<String> {
+        self.door_names()
+            .into_iter()
+            .filter(|name| self.get_door(name).is_ok_and(|door| self.may_play(&door)))
+            .collect()
+    }
+
+    /// Whether the user is allowed to play door at all; sysops always are
+    pub fn may_play(&self, door: &Door) -> bool {
+        if self.is_sysop() {
+            return true;
+        }
+
+        if self
+            .guest
+            .as_ref()
+            .is_some_and(|guest| !guest.doors.is_empty() && !guest.doors.contains(&door.name))
+        {
+            return false;
+        }
+
+        let options = &door.options;
+        let username = &self.user.username;
+        let in_any = |groups: &[String]| groups.iter().any(|group| self.user.in_group(group));
+
+        if options.deny_users.contains(username) || in_any(&options.deny_groups) {
+            return false;
+        }
+
+        if options.allow_users.is_empty() && options.allow_groups.is_empty() {
+            return true;
+        }
+
+        options.allow_users.contains(username) || in_any(&options.allow_groups)
+    }
+
+    /// Turns the user away from door if they aren't allowed to play it
+    pub fn check_may_play(&self, door: &Door) -> Result<()> {
+        if !self.may_play(door) {
+            return Err(anyhow!("Sorry, {} isn't open to you.", door.name));
+        }
+
+        Ok(())
+    }
+
+    /// Minutes username may spend in a session of door, if there's a limit
+    pub fn time_limit(&self, door: &Door, username: &str) -> Option<u32> {
+        [
+            self.time_limit,
+            door.options.time_limit,
+            self.user_time_limits.get(username).copied(),
+            self.guest.as_ref().and_then(|guest| guest.time_limit),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    /// The container image door runs in
+    pub fn door_image<'a>(&'a self, door: &'a Door) -> &'a str {
+        door.options.image.as_deref().unwrap_or(&self.dosemu_image)
+    }
+
+    /// Every container image doors run in
+    pub fn images(&self) -> Vec<String> {
+        let mut images: Vec<String> = self
+            .doors
+            .values()
+            .filter_map(|door| door.image.clone())
+            .chain(std::iter::once(self.dosemu_image.clone()))
+            .collect();
+
+        images.sort();
+        images.dedup();
+        images
+    }
+
+    /// Whether the named door is in category, ignoring case
+    pub fn in_category(&self, name: &str, category: &str) -> bool {
+        self.doors
+            .get(name)
+            .and_then(|door| door.category.as_deref())
+            .is_some_and(|door_category| door_category.eq_ignore_ascii_case(category))
+    }
+
     pub fn is_sysop(&self) -> bool {
-        if self.user.uid == self.uid.as_raw() || self.user.uid == 0 {
+        if self.guest.is_some() {
+            // The guest account may well be the one doorman runs as
+            false
+        } else if self.user.uid == self.uid.as_raw() || self.user.uid == 0 {
             true
         } else {
             self.sysops.contains(&self.user.username)
         }
     }
 
+    /// Whether the user may take node of door, which might be reserved for someone else
+    pub fn may_use_node(&self, door: &Door, node: i8) -> bool {
+        let Some(reserved) = door.options.reserved_nodes.get(&node) else {
+            return true;
+        };
+
+        reserved.iter().any(|entry| self.is_user_or_member(entry))
+    }
+
+    /// Whether entry is the user's name, @group for a Unix group they're in, or
+    /// @sysops and they're a sysop
+    fn is_user_or_member(&self, entry: &str) -> bool {
+        match entry.strip_prefix('@') {
+            Some("sysops") => self.is_sysop(),
+            Some(group) => self.user.in_group(group),
+            None => entry == self.user.username,
+        }
+    }
+
+    /// The user's security level, for drop files
+    pub fn security_level(&self) -> u16 {
+        if let Some(level) = self.security_levels.get(&self.user.username) {
+            return *level;
+        }
+
+        self.security_levels
+            .iter()
+            .filter(|(entry, _)| entry.starts_with('@') && self.is_user_or_member(entry))
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or(self.default_security_level)
+    }
+
+    pub fn can(&self, capability: Capability) -> bool {
+        self.is_sysop()
+            || (self.guest.is_none()
+                && self
+                    .cosysops
+                    .get(&self.user.username)
+                    .is_some_and(|capabilities| capabilities.contains(&capability)))
+    }
+
     pub fn switch_user(
         &mut self,
         username: &Option<String>,
         uid: Option<u32>,
         display_name: &Option<String>,
     ) -> Result<()> {
-        if !self.is_sysop() {
+        if !self.can(Capability::Impersonate) {
             return Err(anyhow!("Only sysops can switch identities!"));
         }
 
@@ -207,7 +1186,7 @@ impl Config {
                     user.username = username.clone();
                 }
             } else if let Some(username) = username {
-                user = user::User::from_username(&username)?;
+                user = user::User::from_username(username)?;
             }
 
             if let Some(display_name) = display_name {
@@ -215,8 +1194,25 @@ impl Config {
             }
         }
 
+        if user.username != self.user.username {
+            self.impersonated_by = Some(self.user.username.clone());
+        }
+
+        let from = format!("{} ({})", self.user.username, self.user.uid);
+
         self.user = user;
 
+        audit::record(
+            self,
+            "switch_user",
+            None,
+            None,
+            Some(format!(
+                "{} to {} ({}, {})",
+                from, self.user.username, self.user.uid, self.user.display_name
+            )),
+        );
+
         Ok(())
     }
 
@@ -225,6 +1221,7 @@ impl Config {
         env: &HashMap<&str, String>,
         volumes: &HashMap<PathBuf, PathBuf>,
         labels: &HashMap<&str, String>,
+        door: &DoorOptions,
     ) -> Vec<String> {
         let mut args: Vec<String> = vec![
             format!("--user={}:{}", self.uid, self.gid),
@@ -249,9 +1246,33 @@ impl Config {
             args.push(format!("-l{}={}", key, value));
         }
 
+        if let Some(health_cmd) = &self.health_cmd {
+            args.push(format!("--health-cmd={}", health_cmd));
+        }
+
+        args.push(format!(
+            "--network={}",
+            door.network.as_deref().unwrap_or("none")
+        ));
+
+        if let Some(memory) = &door.limits.memory {
+            args.push(format!("--memory={}", memory));
+        }
+
+        if let Some(cpus) = door.limits.cpus {
+            args.push(format!("--cpus={}", cpus));
+        }
+
+        if let Some(pids) = door.limits.pids {
+            args.push(format!("--pids-limit={}", pids));
+        }
+
         if self.engine.rootless_podman {
             args.push("--userns=keep-id".to_string());
-            args.push("--passwd=false".to_string());
+
+            if self.engine.supports_passwd() {
+                args.push("--passwd=false".to_string());
+            }
         }
 
         debug!("Container run args: {:?}", args);
@@ -266,15 +1287,24 @@ impl Config {
         cmd
     }
 
+    /// ps, set up to print JSON
+    pub fn ps_command(&self) -> Command {
+        let mut cmd = self.container_command("ps");
+
+        cmd.arg(self.engine.ps_json_format());
+        cmd
+    }
+
     pub fn run_container(
         &self,
         env: &HashMap<&str, String>,
         volumes: &HashMap<PathBuf, PathBuf>,
         labels: &HashMap<&str, String>,
+        door: &DoorOptions,
     ) -> Command {
         let mut cmd = self.container_command("run");
 
-        cmd.args(self.run_args(env, volumes, labels));
+        cmd.args(self.run_args(env, volumes, labels, door));
         cmd
     }
 }
@@ -1,6 +1,7 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use serde::Deserialize;
 use serde_json;
+use std::fmt;
 use std::path::PathBuf;
 use std::process::Command;
 use which::which;
@@ -21,11 +22,48 @@ struct PodmanSecurity {
     rootless: bool,
 }
 
+/// Oldest engines that understand every flag doorman can't do without
+const MIN_PODMAN: EngineVersion = EngineVersion(3, 0, 0);
+const MIN_DOCKER: EngineVersion = EngineVersion(20, 10, 0);
+
+/// podman learned --passwd in 4.0; older ones just go without it
+const PODMAN_PASSWD: EngineVersion = EngineVersion(4, 0, 0);
+
+/// docker learned 'ps --format=json' in 23.0; older ones need a template
+const DOCKER_PS_JSON: EngineVersion = EngineVersion(23, 0, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EngineVersion(u32, u32, u32);
+impl EngineVersion {
+    /// Finds the version in --version output, i.e. "Docker version 24.0.7, build afdd53b"
+    fn parse(output: &str) -> Option<EngineVersion> {
+        output.split_whitespace().find_map(|word| {
+            let mut parts = word
+                .trim_end_matches(',')
+                .split(['.', '-', '+'])
+                .map(|part| part.parse::<u32>());
+
+            match (parts.next(), parts.next()) {
+                (Some(Ok(major)), Some(Ok(minor))) => Some(EngineVersion(
+                    major,
+                    minor,
+                    parts.next().and_then(|patch| patch.ok()).unwrap_or(0),
+                )),
+                _ => None,
+            }
+        })
+    }
+}
+impl fmt::Display for EngineVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
 fn is_podman(path: &PathBuf) -> Result<bool> {
     debug!("Checking for podman...");
 
-    let cmd = Command::new(path).arg("--version").output()?;
-    let output = String::from_utf8(cmd.stdout)?.to_uppercase();
+    let output = engine_version(path)?.to_uppercase();
 
     if output.starts_with("PODMAN ") {
         debug!("Container engine is podman");
@@ -36,7 +74,7 @@ fn is_podman(path: &PathBuf) -> Result<bool> {
     }
 }
 
-fn is_rootless_podman(path: &PathBuf) -> Result<bool> {
+pub fn is_rootless_podman(path: &PathBuf) -> Result<bool> {
     if !is_podman(path)? {
         return Ok(false);
     }
@@ -60,27 +98,67 @@ fn is_rootless_podman(path: &PathBuf) -> Result<bool> {
     }
 }
 
+pub fn find_engine() -> Result<PathBuf> {
+    debug!("Looking for podman in PATH...");
+
+    which("podman")
+        .or_else(|_| {
+            debug!("Looking for docker in PATH...");
+            which("docker")
+        })
+        .with_context(|| "Couldn't find podman or docker in PATH")
+}
+
+pub fn engine_version(path: &PathBuf) -> Result<String> {
+    let cmd = Command::new(path).arg("--version").output()?;
+
+    Ok(String::from_utf8(cmd.stdout)?.trim().to_string())
+}
+
 pub struct ContainerEngine {
     pub path: PathBuf,
     pub rootless_podman: bool,
+    pub podman: bool,
+
+    /// None if the version couldn't be worked out; doorman assumes the best then
+    pub version: Option<EngineVersion>,
 }
 impl ContainerEngine {
     pub fn new(
         engine_path: &Option<PathBuf>,
         rootless_podman: &Option<bool>,
     ) -> Result<ContainerEngine> {
-        let path = engine_path.clone().unwrap_or_else(|| {
-            debug!("Looking for podman in PATH...");
-            which("podman").unwrap_or_else(|_| {
-                debug!("Looking for docker in PATH...");
-                which("docker")
-                    .with_context(|| "Couldn't find podman or docker in PATH")
-                    .unwrap()
-            })
-        });
+        let path = match engine_path {
+            Some(path) => path.clone(),
+            None => find_engine()?,
+        };
 
         debug!("Container engine path: {}", path.display());
 
+        let version_output = engine_version(&path)
+            .with_context(|| format!("Couldn't run {} --version", path.display()))?;
+        let podman = version_output.to_uppercase().starts_with("PODMAN ");
+        let version = EngineVersion::parse(&version_output);
+        let (name, minimum) = if podman {
+            ("podman", MIN_PODMAN)
+        } else {
+            ("docker", MIN_DOCKER)
+        };
+
+        debug!("Container engine is {} {:?}", name, version);
+
+        if let Some(version) = version {
+            if version < minimum {
+                return Err(anyhow!(
+                    "{} {} is too old; doorman needs {} {} or newer",
+                    name,
+                    version,
+                    name,
+                    minimum
+                ));
+            }
+        }
+
         let rootless_podman = rootless_podman.unwrap_or_else(|| {
             is_rootless_podman(&path)
                 .with_context(|| "Failed while checking for rootless podman")
@@ -90,6 +168,27 @@ impl ContainerEngine {
         Ok(ContainerEngine {
             path,
             rootless_podman,
+            podman,
+            version,
         })
     }
+
+    fn at_least(&self, version: EngineVersion) -> bool {
+        self.version
+            .is_none_or(|engine_version| engine_version >= version)
+    }
+
+    /// Whether run understands --passwd
+    pub fn supports_passwd(&self) -> bool {
+        self.podman && self.at_least(PODMAN_PASSWD)
+    }
+
+    /// --format argument that makes ps print JSON
+    pub fn ps_json_format(&self) -> &'static str {
+        if self.podman || self.at_least(DOCKER_PS_JSON) {
+            "--format=json"
+        } else {
+            "--format={{json .}}"
+        }
+    }
 }
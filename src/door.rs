@@ -1,29 +1,88 @@
 //use super::cfg::{Config, Door, User};
-use super::config;
+use super::audit;
+use super::bulletin;
+use super::charset;
+use super::checksum;
+use super::config::{self, Capability};
 use super::dos::Templates;
+use super::guest;
+use super::hooks::{self, HookSession};
+use super::lock;
+use super::menu;
+use super::monitor;
+use super::page;
+use super::pull;
+use super::queue;
+use super::quota;
+use super::requires;
+use super::session::SessionState;
+use super::signature;
+use super::stats;
+use super::telemetry::{SpanId, Trace};
+use super::translate::Translator;
 use super::user::User;
-use super::{LaunchArgs, SysopCmdArgs};
-use log::debug;
+use super::webhook::{self, WebhookEvent};
+use super::who::{self, WhoNode};
+use super::{AttachArgs, Charset, LaunchArgs, SysopCmdArgs};
+use log::{debug, warn};
 use anyhow::{anyhow, Context, Result};
-use chrono::Local;
+use chrono::{Local, Utc};
 use fs4::FileExt;
+use nix::sys::stat;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::env;
+use std::ffi::OsStr;
 use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+use terminal_size::{Height, Width};
+use which::which;
+
+/// File in the node rundir a door can write another door's name to, to launch it next
+const HANDOFF_FILE: &str = "NEXTDOOR.TXT";
+
+/// DOS drive for the per-session scratch tmpfs
+const SCRATCH_DRIVE: &str = "x:";
+
+/// Where door terminfo entries are mounted inside the container
+const TERMINFO_DIR: &str = "/opt/doorman/terminfo";
 
 #[derive(Serialize, Debug)]
 struct LaunchVars<'a> {
     user: &'a User,
+    first_name: &'a str,
+    last_name: &'a str,
+    door: &'a str,
     node: i8,
+    max_nodes: i8,
+    node_rundir: String,
+    bbs_name: Option<&'a str>,
+    sysop_name: Option<&'a str>,
+    sysop_first_name: Option<&'a str>,
+    sysop_last_name: Option<&'a str>,
+    location: Option<&'a str>,
+    time_limit: Option<u32>,
+    minutes_remaining: Option<u32>,
+    seconds_remaining: Option<u32>,
+    security_level: u16,
+    rows: u16,
+    cols: u16,
     current_time: String,
+    scratch_drive: Option<&'static str>,
+    page_drive: Option<&'static str>,
+    page_file: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
 struct BatchCommands {
     commands: String,
+    scratch_drive: Option<&'static str>,
+    page_drive: Option<&'static str>,
 }
 
 fn get_term() -> String {
@@ -36,7 +95,198 @@ fn get_term() -> String {
     String::from("xterm")
 }
 
-fn make_lockfile(path: &Path) -> Result<fs::File> {
+fn door_term(door: &config::Door) -> String {
+    door.options.term.clone().unwrap_or_else(get_term)
+}
+
+fn mount_extra_volumes(door: &config::Door, volumes: &mut HashMap<PathBuf, PathBuf>) -> Result<()> {
+    for (host_path, container_path) in door.options.extra_volumes.iter() {
+        if !host_path.exists() {
+            return Err(anyhow!(
+                "Couldn't find {} to mount for {}",
+                host_path.display(),
+                door.name
+            ));
+        }
+
+        if volumes.contains_key(host_path) {
+            return Err(anyhow!(
+                "{} is already mounted for {}",
+                host_path.display(),
+                door.name
+            ));
+        }
+
+        volumes.insert(host_path.clone(), container_path.clone());
+    }
+
+    Ok(())
+}
+
+/// Mounts the door's terminfo entry into the container and points TERMINFO at it
+fn mount_terminfo(
+    door: &config::Door,
+    env: &mut HashMap<&str, String>,
+    volumes: &mut HashMap<PathBuf, PathBuf>,
+) -> Result<()> {
+    let Some(terminfo) = &door.options.terminfo else {
+        return Ok(());
+    };
+
+    if !terminfo.is_file() {
+        return Err(anyhow!(
+            "Couldn't find terminfo entry {} for {}",
+            terminfo.display(),
+            door.name
+        ));
+    }
+
+    let name = terminfo
+        .file_name()
+        .and_then(OsStr::to_str)
+        .ok_or(anyhow!("Bad terminfo path {}", terminfo.display()))?;
+    let initial = name.chars().next().unwrap_or('_');
+
+    volumes.insert(
+        terminfo.clone(),
+        Path::new(TERMINFO_DIR).join(initial.to_string()).join(name),
+    );
+    env.insert("TERMINFO", TERMINFO_DIR.to_string());
+
+    Ok(())
+}
+
+fn find_session(door: &config::Door, config: &config::Config) -> Option<WhoNode> {
+    match who::who(&Some(door.name.clone()), config) {
+        Ok(nodes) => nodes
+            .into_iter()
+            .find(|node| node.user == config.user.username && node.node.is_some()),
+        Err(err) => {
+            warn!("Couldn't check for existing sessions: {:#}", err);
+            None
+        }
+    }
+}
+
+pub fn session_log_path(config: &config::Config, door_name: &str, node: i8) -> PathBuf {
+    config.rundir.join(format!("{0}.{1}.log", door_name, node))
+}
+
+fn shell_quote(arg: &OsStr) -> String {
+    format!("'{}'", arg.to_string_lossy().replace('\'', "'\\''"))
+}
+
+fn exec_launch(
+    config: &config::Config,
+    door: &config::Door,
+    container_id: &str,
+    node: i8,
+    append: bool,
+) -> Result<()> {
+    let log_path = session_log_path(config, &door.name, node);
+    let mut exec = config.container_command("exec");
+
+    exec.arg("-ti").arg(container_id).arg("launch.sh");
+
+    // Record the session with script(1) if we can, so spectators can follow along
+    let mut cmd = match which("script") {
+        Ok(script) => {
+            let command_line: Vec<String> = std::iter::once(exec.get_program())
+                .chain(exec.get_args())
+                .map(shell_quote)
+                .collect();
+
+            let mut cmd = Command::new(script);
+
+            cmd.arg("--quiet").arg("--flush").arg("--return");
+
+            if append {
+                cmd.arg("--append");
+            }
+
+            cmd.arg("--command")
+                .arg(command_line.join(" "))
+                .arg(&log_path);
+            cmd
+        }
+        Err(_) => {
+            debug!("script(1) not found, session won't be recorded");
+            exec
+        }
+    };
+
+    let node_rundir = config.rundir.join(format!("{0}.{1}", door.name, node));
+    let translator = Translator::load(&node_rundir)?;
+
+    let status = match translator {
+        Some(translator) => {
+            let mut client = cmd
+                .stdout(Stdio::piped())
+                .spawn()
+                .with_context(|| "While starting client")?;
+
+            if let Some(mut output) = client.stdout.take() {
+                if let Err(err) = translator.copy(&mut output, &mut io::stdout()) {
+                    warn!("{:#}", err);
+                }
+            }
+
+            client.wait().with_context(|| "While waiting for client")?
+        }
+        None => cmd.status().with_context(|| "While starting client")?,
+    };
+
+    debug!("Client exited with {}", status);
+
+    Ok(())
+}
+
+fn split_name(name: &str) -> (&str, &str) {
+    let (first_name, last_name) = name.split_once(' ').unwrap_or((name, ""));
+
+    (first_name, last_name.trim())
+}
+
+/// Caller environment variables matching the allowlist
+fn passed_env(allowed: &[String]) -> Vec<(String, String)> {
+    env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .filter(|(name, _)| {
+            allowed
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == pattern,
+                })
+        })
+        .collect()
+}
+
+/// How the session was started, for who
+fn session_source(source: &Option<String>) -> String {
+    if let Some(source) = source {
+        return source.clone();
+    }
+
+    if let Ok(source) = env::var("DOORMAN_SOURCE") {
+        return source;
+    }
+
+    if env::var_os("SSH_CONNECTION").is_some() || env::var_os("SSH_CLIENT").is_some() {
+        "ssh".to_string()
+    } else {
+        "local".to_string()
+    }
+}
+
+fn terminal_size() -> (u16, u16) {
+    match terminal_size::terminal_size() {
+        Some((Width(cols), Height(rows))) => (rows, cols),
+        None => (24, 80),
+    }
+}
+
+pub fn make_lockfile(path: &Path) -> Result<fs::File> {
     fs::File::options()
         .read(true)
         .write(true)
@@ -46,35 +296,355 @@ fn make_lockfile(path: &Path) -> Result<fs::File> {
         .with_context(|| format!("Couldn't open lockfile {}", path.display()))
 }
 
-fn make_node_lockfile(
-    max_nodes: i8,
-    door_name: &str,
+/// Locks the first free node the user may take, if there is one
+pub fn try_node_lockfile(
+    door: &config::Door,
     config: &config::Config,
-) -> Result<(i8, PathBuf, std::fs::File)> {
-    let mut node: i8 = 1;
+) -> Result<Option<(i8, PathBuf, std::fs::File)>> {
+    lock_free_node(door, config, door.options.node_numbers()?)
+}
+
+/// Locks the first free node that's reserved for the user, if there is one; those
+/// don't go to whoever's waiting in line, since they couldn't take them anyway
+pub fn try_reserved_node_lockfile(
+    door: &config::Door,
+    config: &config::Config,
+) -> Result<Option<(i8, PathBuf, std::fs::File)>> {
+    let reserved = door
+        .options
+        .node_numbers()?
+        .into_iter()
+        .filter(|node| door.options.reserved_nodes.contains_key(node));
+
+    lock_free_node(door, config, reserved)
+}
+
+fn lock_free_node(
+    door: &config::Door,
+    config: &config::Config,
+    nodes: impl IntoIterator<Item = i8>,
+) -> Result<Option<(i8, PathBuf, std::fs::File)>> {
+    let door_name = &door.name;
+
+    for node in nodes {
+        if !config.may_use_node(door, node) {
+            debug!(
+                "Node {} of {} is reserved for someone else",
+                node, door_name
+            );
+            continue;
+        }
 
-    while node <= max_nodes {
         let node_lockfile_path = config.rundir.join(format!("{0}.{1}.lock", door_name, node));
         let node_lockfile = make_lockfile(&node_lockfile_path)
             .with_context(|| format!("Failed to lock node {} for door '{}'", node, door_name))?;
 
         if node_lockfile.try_lock_exclusive().is_ok() {
-            return Ok((node, node_lockfile_path, node_lockfile));
+            return Ok(Some((node, node_lockfile_path, node_lockfile)));
         }
+    }
 
-        node += 1;
+    Ok(None)
+}
+
+pub fn make_node_lockfile(
+    door: &config::Door,
+    config: &config::Config,
+) -> Result<(i8, PathBuf, std::fs::File)> {
+    try_node_lockfile(door, config)?.ok_or(anyhow!("All nodes for {0} are busy!", door.name))
+}
+
+/// Locks door for the caller while they play it, so they can't take a second node
+fn make_player_lockfile(config: &config::Config, door: &config::Door) -> Result<fs::File> {
+    let path = config.rundir.join(format!(
+        "{}.{}.player.lock",
+        door.name, config.user.username
+    ));
+    let lockfile = make_lockfile(&path).with_context(|| "While locking player")?;
+
+    if lockfile.try_lock_exclusive().is_err() {
+        return Err(anyhow!(
+            "You're already playing {}, and it only lets you play one node at a time.",
+            door.name
+        ));
     }
 
-    return Err(anyhow!("All nodes for {0} are busy!", door_name));
+    Ok(lockfile)
 }
 
-pub fn launch(args: &LaunchArgs, mut config: config::Config) -> Result<()> {
-    let door = config.get_door(&args.door)?;
+fn launch_vars<'a>(
+    config: &'a config::Config,
+    door: &'a config::Door,
+    node: i8,
+    node_rundir: &Path,
+) -> LaunchVars<'a> {
+    let (first_name, last_name) = split_name(&config.user.display_name);
+    let sysop_name = config.sysop_name.as_deref().map(split_name);
+
+    let (rows, cols) = terminal_size();
+    let time_limit = config.time_limit(door, &config.user.username);
+
+    LaunchVars {
+        user: &config.user,
+        first_name,
+        last_name,
+        door: &door.name,
+        node,
+        max_nodes: door.options.last_node(),
+        node_rundir: node_rundir.display().to_string(),
+        bbs_name: config.bbs_name.as_deref(),
+        sysop_name: config.sysop_name.as_deref(),
+        sysop_first_name: sysop_name.map(|(first_name, _)| first_name),
+        sysop_last_name: sysop_name.map(|(_, last_name)| last_name),
+        location: config.location.as_deref(),
+        time_limit,
+        minutes_remaining: time_limit,
+        seconds_remaining: time_limit.map(|minutes| minutes * 60),
+        security_level: config.security_level(),
+        rows,
+        cols,
+        current_time: Local::now().format("%H:%M").to_string(),
+        scratch_drive: scratch_drive(door),
+        page_drive: page_drive(door),
+        page_file: page_drive(door).map(|drive| format!("{}\\{}", drive, page::inbox_name(node))),
+    }
+}
+
+fn scratch_drive(door: &config::Door) -> Option<&'static str> {
+    door.options.scratch.as_ref().map(|_| SCRATCH_DRIVE)
+}
+
+fn page_drive(door: &config::Door) -> Option<&'static str> {
+    door.options.paging.then_some(page::PAGE_DRIVE)
+}
+
+/// Renders doorman.bat as it would be written for the current user on node
+pub fn render_launch_batch(
+    config: &config::Config,
+    door: &config::Door,
+    node: i8,
+) -> Result<String> {
+    let node_rundir = config.rundir.join(format!("{0}.{1}", door.name, node));
+    let vars = launch_vars(config, door, node, &node_rundir);
+    let templates = Templates::new(&config.template_dir(), door.options.codepage);
+
+    let commands = BatchCommands {
+        commands: templates
+            .render_string(&door.options.launch_commands, &vars)
+            .with_context(|| format!("Couldn't generate batch commands for {}", door.name))?,
+        scratch_drive: scratch_drive(door),
+        page_drive: page_drive(door),
+    };
 
+    templates.render_template("doorman.bat", &commands)
+}
+
+/// Checks whether someone is holding a lock that would block taking it exclusively
+/// (or shared), without creating the lockfile or taking the lock ourselves; a probe
+/// that briefly held the lock would make a launch racing with it fail
+fn is_locked(path: &Path, exclusive: bool) -> Result<bool> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err).with_context(|| format!("Couldn't stat {}", path.display())),
+    };
+
+    // /proc/locks identifies files as major:minor:inode, e.g.
+    // 1: FLOCK  ADVISORY  WRITE 1234 00:1f:5678 0 EOF
+    let id = format!(
+        "{:02x}:{:02x}:{}",
+        stat::major(metadata.dev()),
+        stat::minor(metadata.dev()),
+        metadata.ino()
+    );
+
+    let locks = fs::read_to_string("/proc/locks").context("Couldn't read /proc/locks")?;
+
+    Ok(locks.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        // Lines with "->" are processes still waiting for the lock
+        fields.len() >= 6
+            && fields[1] == "FLOCK"
+            && fields[5] == id
+            && (exclusive || fields[3] == "WRITE")
+    }))
+}
+
+/// Whether a sysop has the door locked for maintenence
+pub fn in_maintenence(config: &config::Config, door_name: &str) -> Result<bool> {
+    Ok(lock::maintenance_lock(config, door_name)?.is_some()
+        || is_locked(&config.rundir.join(format!("{}.lock", door_name)), false)?)
+}
+
+/// Whether someone is playing on node
+pub fn node_busy(config: &config::Config, door_name: &str, node: i8) -> Result<bool> {
+    is_locked(
+        &config.rundir.join(format!("{0}.{1}.lock", door_name, node)),
+        true,
+    )
+}
+
+/// Players asking for raw output are on a CP437 terminal
+fn launch_charset(args: &LaunchArgs) -> Charset {
+    charset::detect(args.charset.or(args.raw.then_some(Charset::Cp437)))
+}
+
+/// The door a player asked for next by writing its name to NEXTDOOR.TXT, or the one
+/// set as next_door; "menu" means going back to the door menu
+fn next_door(config: &config::Config, door: &config::Door, node_rundir: &Path) -> Option<String> {
+    let handoff_path = node_rundir.join(HANDOFF_FILE);
+    let handoff = fs::read_to_string(&handoff_path)
+        .ok()
+        .map(|handoff| handoff.trim().to_string())
+        .filter(|handoff| !handoff.is_empty());
+
+    if handoff.is_some() {
+        if let Err(err) = fs::remove_file(&handoff_path) {
+            warn!("Couldn't remove {}: {:#}", handoff_path.display(), err);
+        }
+    }
+
+    let next = handoff.or_else(|| door.options.next_door.clone())?;
+
+    if next.eq_ignore_ascii_case("menu") {
+        return Some("menu".to_string());
+    }
+
+    // Doors running under DOS will probably shout the name
+    let found = config
+        .door_names()
+        .into_iter()
+        .find(|name| name.eq_ignore_ascii_case(&next));
+
+    if found.is_none() {
+        eprintln!(
+            "{} asked for door '{}', but there's no such door.",
+            door.name, next
+        );
+    }
+
+    found
+}
+
+pub fn launch(args: &LaunchArgs, mut config: config::Config) -> Result<()> {
     if args.user.is_some() || args.user_id.is_some() || args.display_name.is_some() {
         config.switch_user(&args.user, args.user_id, &args.display_name)?;
     }
 
+    if config.guest.is_some() {
+        guest::sign_in(&mut config)?;
+    } else {
+        config.user.display_name = config.display_names.apply(&config.user)?;
+    }
+
+    let charset = launch_charset(args);
+    let mut door_name = match &args.door {
+        Some(door_name) => door_name.clone(),
+        None => match menu::choose_door(&config, charset)? {
+            Some(door_name) => door_name,
+            None => return Ok(()),
+        },
+    };
+
+    loop {
+        let mut trace = Trace::new(&config.otlp);
+        let span = trace.start("launch", None);
+
+        trace.attribute(span, "doorman.door", &door_name);
+
+        let result = launch_door(args, &config, &door_name, &mut trace, span);
+
+        if let Err(err) = &result {
+            let detail = format!("{:#}", err);
+
+            webhook::notify(
+                &config,
+                WebhookEvent::Error,
+                &door_name,
+                None,
+                Some(&detail),
+            );
+        }
+
+        trace.finish(span, &result);
+        trace.export();
+
+        door_name = match result? {
+            Some(next) if next == "menu" => match menu::choose_door(&config, charset)? {
+                Some(door_name) => door_name,
+                None => return Ok(()),
+            },
+            Some(next) => next,
+            None => return Ok(()),
+        };
+    }
+}
+
+/// Launches a door, returning the door to launch next, if any
+fn launch_door(
+    args: &LaunchArgs,
+    config: &config::Config,
+    door_name: &str,
+    trace: &mut Trace,
+    span: SpanId,
+) -> Result<Option<String>> {
+    let door = config.get_door(door_name)?;
+    let source = session_source(&args.source);
+
+    config.check_may_play(&door)?;
+
+    trace.attribute(span, "doorman.user", &config.user.username);
+    trace.attribute(span, "doorman.source", &source);
+
+    let existing = find_session(&door, config);
+
+    if let Some(session) = &existing {
+        let node = session.node.unwrap_or(0);
+        let question = format!(
+            "You're already playing {} on node {}. Rejoin that session?",
+            door.name, node
+        );
+
+        if menu::confirm(&question)? {
+            trace.attribute(span, "doorman.node", node);
+            trace.attribute(span, "doorman.rejoin", true);
+            audit::record(config, "rejoin", Some(&door.name), Some(node), None);
+
+            let session_span = trace.start("session", Some(span));
+            let result = exec_launch(config, &door, &session.container_id, node, true);
+
+            trace.finish(session_span, &result);
+            result?;
+
+            let node_rundir = config.rundir.join(format!("{0}.{1}", door.name, node));
+
+            return Ok(next_door(config, &door, &node_rundir));
+        }
+
+        if !door.options.allow_multiple {
+            return Err(anyhow!(
+                "You can only play {} on one node at a time; 'doorman attach {}' will get you back in",
+                door.name,
+                door.name
+            ));
+        }
+    }
+
+    let prepare_span = trace.start("prepare", Some(span));
+
+    if let Err(err) = config.check_storage() {
+        trace.event(span, "doorman.storage_problem", &format!("{:#}", err));
+        return Err(err.context("Sorry, the board can't start doors right now"));
+    }
+
+    lock::check(config, &door)?;
+    requires::check_requirements(&door)?;
+    quota::check(config, &door)?;
+    config.launch_limits.check()?;
+    pull::ensure_image(config, config.door_image(&door))?;
+    signature::check_image(config, config.door_image(&door))?;
+
     let door_lockfile_path = config.rundir.join(format!("{}.lock", door.name));
     let door_lockfile = make_lockfile(&door_lockfile_path).with_context(|| "While locking door")?;
 
@@ -85,8 +655,49 @@ pub fn launch(args: &LaunchArgs, mut config: config::Config) -> Result<()> {
         ));
     }
 
-    let (node, node_lockfile_path, node_lockfile) =
-        make_node_lockfile(door.options.max_nodes, &door.name, &config)?;
+    // Held until the session ends, to stop the same player launching twice at once
+    let _player_lockfile = if door.options.allow_multiple {
+        None
+    } else {
+        Some(make_player_lockfile(config, &door)?)
+    };
+
+    let wait = (args.wait || door.options.wait_for_node) && !args.no_wait;
+
+    let (node, node_lockfile_path, node_lockfile) = if let Some(reserved) =
+        try_reserved_node_lockfile(&door, config)?
+    {
+        reserved
+    } else if wait {
+        queue::wait_for_node(config, &door)?
+    } else {
+        // Nodes that free up go to whoever's been waiting for them
+        let waiting = queue::waiting(config, &door.name)?;
+
+        if waiting > 0 {
+            return Err(anyhow!(
+                "All nodes for {} are busy, with {} waiting in line; 'doorman launch --wait {}' will get you in line too",
+                door.name,
+                waiting,
+                door.name
+            ));
+        }
+
+        make_node_lockfile(&door, config).map_err(|err| {
+            // Don't leave someone who said no to rejoining with nowhere to go
+            match &existing {
+                Some(session) => err.context(format!(
+                    "You're still playing {} on node {}; 'doorman attach {}' will get you back in",
+                    door.name,
+                    session.node.unwrap_or(0),
+                    door.name
+                )),
+                None => err,
+            }
+        })?
+    };
+
+    trace.attribute(span, "doorman.node", node);
 
     let node_rundir = config.rundir.join(format!("{0}.{1}", door.name, node));
 
@@ -98,57 +709,120 @@ pub fn launch(args: &LaunchArgs, mut config: config::Config) -> Result<()> {
     fs::create_dir_all(&node_rundir)
         .with_context(|| format!("Couldn't create node rundir {}", node_rundir.display()))?;
 
-    let vars = LaunchVars {
-        user: &config.user,
-        node,
-        current_time: Local::now().format("%H:%M").to_string(),
-    };
+    let vars = launch_vars(config, &door, node, &node_rundir);
 
-    let templates = Templates::new();
+    let templates = Templates::new(&config.template_dir(), door.options.codepage)
+        .shorten_filenames(door.options.shorten_filenames);
 
-    templates.write_dos("door.sys", &node_rundir, &vars)?;
+    for dropfile in door.options.dropfiles.iter() {
+        let filename = dropfile.filename(node)?;
+
+        templates
+            .write_dos_as(dropfile.template(), &filename, &node_rundir, &vars)
+            .with_context(|| format!("Couldn't write {} for {}", filename, door.name))?;
+    }
 
     let commands = BatchCommands {
         commands: templates
             .render_string(&door.options.launch_commands, &vars)
             .with_context(|| format!("Couldn't generate batch commands for {}", door.name))?,
+        scratch_drive: scratch_drive(&door),
+        page_drive: page_drive(&door),
     };
 
     templates.write_dos("doorman.bat", &node_rundir, &commands)?;
 
-    let env = HashMap::from([
-        ("TERM", get_term()),
-        (
-            "DOORMAN_RAW",
-            if args.raw {
-                "1".to_string()
-            } else {
-                "0".to_string()
-            },
-        ),
+    let allowed: Vec<String> = config
+        .pass_env
+        .iter()
+        .chain(door.options.pass_env.iter())
+        .cloned()
+        .collect();
+    let passed = passed_env(&allowed);
+
+    let mut env: HashMap<&str, String> = passed
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.clone()))
+        .collect();
+
+    debug!("Passing environment variables: {:?}", env.keys());
+
+    let raw = (door.options.raw || args.raw) && !args.no_raw;
+
+    env.extend([
+        ("TERM", door_term(&door)),
+        ("DOORMAN_RAW", if raw { "1" } else { "0" }.to_string()),
     ]);
 
-    let volumes = HashMap::from([
+    // The table stays in the node rundir so attach translates the same way
+    if !raw && !door.options.translate.is_empty() {
+        door.options.translate.write(&node_rundir)?;
+    }
+
+    let mut volumes = HashMap::from([
         (node_rundir.clone(), PathBuf::from("/mnt/doorman")),
         (door.options.door_path.clone(), PathBuf::from("/mnt/door")),
         (door_lockfile_path.clone(), PathBuf::from("/mnt/door.lock")),
         (node_lockfile_path, PathBuf::from("/mnt/node.lock")),
     ]);
 
-    let labels = HashMap::from([
+    mount_terminfo(&door, &mut env, &mut volumes)?;
+    mount_extra_volumes(&door, &mut volumes)?;
+
+    if door.options.paging {
+        volumes.insert(
+            page::prepare(config, &door, node)?,
+            PathBuf::from("/mnt/pages"),
+        );
+    }
+
+    let mut labels = HashMap::from([
         ("doorman.door", door.name.clone()),
         ("doorman.node", format!("{}", node)),
         ("doorman.user", config.user.username.clone()),
+        ("doorman.display_name", config.user.display_name.clone()),
+        ("doorman.source", source),
         (
             "doorman.rundir",
             format!("{}", node_rundir.clone().display()),
         ),
     ]);
 
-    let run = config
-        .run_container(&env, &volumes, &labels)
+    if let Some(impersonated_by) = &config.impersonated_by {
+        labels.insert("doorman.impersonated_by", impersonated_by.clone());
+    }
+
+    let time_limit = config.time_limit(&door, &config.user.username);
+
+    if let Some(minutes) = time_limit {
+        labels.insert("doorman.time_limit", minutes.to_string());
+    }
+
+    hooks::pre_launch(
+        config,
+        &HookSession {
+            door: &door,
+            node,
+            rundir: &node_rundir,
+            container_id: None,
+            exit_code: None,
+        },
+    )?;
+
+    quota::claim_launch(config, &door)?;
+
+    trace.end(prepare_span);
+
+    let start_span = trace.start("start container", Some(span));
+    let mut run = config.run_container(&env, &volumes, &labels, &door.options);
+
+    if let Some(size) = &door.options.scratch {
+        run.arg(format!("--tmpfs=/mnt/scratch:size={}", size));
+    }
+
+    let run = run
         .arg("-d")
-        .arg(&config.dosemu_image)
+        .arg(config.door_image(&door))
         .arg("wait-for-launch.sh")
         .stdout(Stdio::piped())
         .spawn()
@@ -178,38 +852,152 @@ pub fn launch(args: &LaunchArgs, mut config: config::Config) -> Result<()> {
 
     debug!("Container ID: {0}", container_id.trim());
 
+    if let Err(err) = stats::record_play(config, &door) {
+        warn!("Couldn't record play statistics: {:#}", err);
+    }
+
+    let state = SessionState {
+        container_id: container_id.trim().to_string(),
+        user: config.user.username.clone(),
+        uid: Some(config.user.uid),
+        display_name: Some(config.user.display_name.clone()),
+        door: door.name.clone(),
+        node,
+        source: labels.get("doorman.source").cloned(),
+        impersonated_by: config.impersonated_by.clone(),
+        since: Utc::now(),
+        pid: std::process::id(),
+        rundir: node_rundir.clone(),
+        time_limit,
+    };
+
+    // The monitor wraps the session up when the container exits
+    let monitored = state
+        .write(config)
+        .and_then(|_| monitor::spawn(&door, node));
+
+    if let Err(err) = monitored {
+        warn!("Session won't be recorded: {:#}", err);
+    }
+
+    audit::record(
+        config,
+        "launch",
+        Some(&door.name),
+        Some(node),
+        Some(state.container_id.clone()),
+    );
+    webhook::notify(config, WebhookEvent::Launch, &door.name, Some(node), None);
+
+    trace.end(start_span);
     node_lockfile.unlock()?;
 
-    config
-        .container_command("exec")
-        .arg("-ti")
-        .arg(container_id.trim())
-        .arg("launch.sh")
-        .status()
-        .with_context(|| "While starting client")?;
+    if let Some(impersonated_by) = &config.impersonated_by {
+        eprintln!(
+            "*** {} is playing {} as {} ({}) ***",
+            impersonated_by, door.name, config.user.username, config.user.display_name
+        );
+    }
 
-    Ok(())
+    let session_span = trace.start("session", Some(span));
+    let result = exec_launch(config, &door, container_id.trim(), node, false);
+
+    trace.finish(session_span, &result);
+    result?;
+
+    Ok(next_door(config, &door, &node_rundir))
+}
+
+/// Reconnects to a session that's still running, i.e. after a dropped connection
+pub fn attach(args: &AttachArgs, config: &config::Config) -> Result<()> {
+    let door_name = match &args.door {
+        Some(door_name) => Some(config.get_door(door_name)?.name),
+        None => None,
+    };
+
+    let sessions: Vec<WhoNode> = who::who(&door_name, config)?
+        .into_iter()
+        .filter(|session| session.user == config.user.username && session.node.is_some())
+        .collect();
+
+    let session = match sessions.as_slice() {
+        [] => return Err(anyhow!("You aren't playing anything right now.")),
+        [session] => session,
+        _ => {
+            let doors: Vec<&str> = sessions
+                .iter()
+                .map(|session| session.door.as_str())
+                .collect();
+
+            return Err(anyhow!(
+                "You're playing several doors ({}); say which one to attach to",
+                doors.join(", ")
+            ));
+        }
+    };
+
+    let door = config.get_door(&session.door)?;
+    let node = session.node.unwrap_or(0);
+
+    eprintln!("Reattaching to {} on node {}...", door.name, node);
+    audit::record(config, "attach", Some(&door.name), Some(node), None);
+
+    exec_launch(config, &door, &session.container_id, node, true)
 }
 
 pub fn configure(args: &SysopCmdArgs, config: &config::Config) -> Result<()> {
     let door = config.get_door(&args.door)?;
+
+    config.check_may_play(&door)?;
     sysop_command(
         args,
         config,
         &door,
         "configure",
+        Capability::Configure,
         &door.options.configure_commands,
     )
 }
 
 pub fn nightly(args: &SysopCmdArgs, config: &config::Config) -> Result<()> {
     let door = config.get_door(&args.door)?;
+
+    if !door.options.checksum_files.is_empty() {
+        match checksum::verify(config, &door) {
+            Ok(problems) => {
+                for problem in problems {
+                    eprintln!("WARNING: {}: {}", door.name, problem);
+                }
+            }
+            Err(err) => warn!("Couldn't verify checksums for {}: {:#}", door.name, err),
+        }
+    }
+
     sysop_command(
         args,
         config,
         &door,
         "nightly",
+        Capability::Nightly,
         &door.options.nightly_commands,
+    )?;
+
+    if door.options.warmup_commands.is_some() {
+        warmup(args, config)?;
+    }
+
+    Ok(())
+}
+
+pub fn warmup(args: &SysopCmdArgs, config: &config::Config) -> Result<()> {
+    let door = config.get_door(&args.door)?;
+    sysop_command(
+        args,
+        config,
+        &door,
+        "warmup",
+        Capability::Nightly,
+        &door.options.warmup_commands,
     )
 }
 
@@ -218,9 +1006,76 @@ fn sysop_command(
     config: &config::Config,
     door: &config::Door,
     command: &str,
+    capability: Capability,
+    template: &Option<String>,
+) -> Result<()> {
+    let mut trace = Trace::new(&config.otlp);
+    let span = trace.start(command, None);
+
+    trace.attribute(span, "doorman.door", &door.name);
+    trace.attribute(span, "doorman.user", &config.user.username);
+    trace.attribute(span, "doorman.nowait", args.nowait);
+
+    let result = config
+        .check_storage()
+        .inspect_err(|err| trace.event(span, "doorman.storage_problem", &format!("{:#}", err)))
+        .and_then(|_| run_sysop_command(args, config, door, command, capability, template));
+
+    let detail = result.as_ref().err().map(|err| format!("{:#}", err));
+
+    audit::record(config, command, Some(&door.name), None, detail.clone());
+
+    if command == "nightly" {
+        webhook::notify(
+            config,
+            WebhookEvent::Nightly,
+            &door.name,
+            None,
+            detail.as_deref(),
+        );
+    }
+
+    if detail.is_some() {
+        webhook::notify(
+            config,
+            WebhookEvent::Error,
+            &door.name,
+            None,
+            detail.as_deref(),
+        );
+    }
+
+    trace.finish(span, &result);
+    trace.export();
+
+    result
+}
+
+/// Takes the door's lock for maintenence, waiting for players to leave unless nowait
+fn lock_door_exclusively(lockfile: &fs::File, nowait: bool, door: &config::Door) -> Result<()> {
+    if !nowait {
+        return Ok(lockfile.lock_exclusive()?);
+    }
+
+    if lockfile.try_lock_exclusive().is_err() {
+        return Err(anyhow!(
+            "Sorry, I couldn't lock the door '{}' exclusively.",
+            door.name
+        ));
+    }
+
+    Ok(())
+}
+
+fn run_sysop_command(
+    args: &SysopCmdArgs,
+    config: &config::Config,
+    door: &config::Door,
+    command: &str,
+    capability: Capability,
     template: &Option<String>,
 ) -> Result<()> {
-    if !config.is_sysop() {
+    if !config.can(capability) {
         return Err(anyhow!("This command is only for sysops!"));
     }
 
@@ -232,19 +1087,13 @@ fn sysop_command(
         ));
     }
 
+    pull::ensure_image(config, config.door_image(door))?;
+    signature::check_image(config, config.door_image(door))?;
+
     let door_lockfile_path = config.rundir.join(format!("{}.lock", door.name));
     let door_lockfile = make_lockfile(&door_lockfile_path)?;
 
-    if args.nowait {
-        if door_lockfile.try_lock_exclusive().is_err() {
-            return Err(anyhow!(
-                "Sorry, I couldn't lock the door '{}' exclusively.",
-                door.name
-            ));
-        }
-    } else {
-        door_lockfile.lock_exclusive()?;
-    }
+    lock_door_exclusively(&door_lockfile, args.nowait, door)?;
 
     let sysop_rundir = config.rundir.join(format!("{}.sysop", door.name));
 
@@ -257,43 +1106,141 @@ fn sysop_command(
     fs::create_dir_all(&sysop_rundir)
         .with_context(|| format!("Couldn't create sysop rundir {}", sysop_rundir.display()))?;
 
-    let templates = Templates::new();
+    let templates = Templates::new(&config.template_dir(), door.options.codepage)
+        .shorten_filenames(door.options.shorten_filenames);
+
     let commands = BatchCommands {
         commands: template.clone().unwrap(),
+        scratch_drive: None,
+        page_drive: None,
     };
 
     templates.write_dos("doorman.bat", &sysop_rundir, commands)?;
 
-    let env = HashMap::from([("TERM", get_term())]);
+    let mut env = HashMap::from([("TERM", door_term(door))]);
 
-    let volumes = HashMap::from([
+    let mut volumes = HashMap::from([
         (sysop_rundir.clone(), PathBuf::from("/mnt/doorman")),
         (door.options.door_path.clone(), PathBuf::from("/mnt/door")),
         (door_lockfile_path, PathBuf::from("/mnt/door.lock")),
     ]);
 
+    mount_terminfo(door, &mut env, &mut volumes)?;
+    mount_extra_volumes(door, &mut volumes)?;
+
     let labels = HashMap::from([
         ("doorman.door", door.name.clone()),
         ("doorman.command", command.to_string()),
         ("doorman.user", config.user.username.clone()),
+        ("doorman.nowait", args.nowait.to_string()),
         (
             "doorman.rundir",
             format!("{}", sysop_rundir.clone().display()),
         ),
     ]);
 
-    let mut run = config
-        .run_container(&env, &volumes, &labels)
-        .arg("-ti")
-        .arg(&config.dosemu_image)
-        .arg(format!("{}.sh", command))
-        .spawn()
-        .with_context(|| format!("While spawning container for door '{}'", door.name))?;
+    let attempts = if command == "nightly" {
+        door.options.nightly_retries + 1
+    } else {
+        1
+    };
+
+    // Scheduled runs don't have a terminal; keep their output for later
+    let interactive = io::stdin().is_terminal();
+    let log_path = config.rundir.join(format!("{}.{}.log", door.name, command));
+    let mut attempt = 1;
+
+    if !interactive {
+        // Every attempt's output goes in the same log, so earlier failures aren't lost
+        fs::File::create(&log_path)
+            .with_context(|| format!("Couldn't create log {}", log_path.display()))?;
+    }
+
+    loop {
+        if attempt > 1 {
+            lock_door_exclusively(&door_lockfile, args.nowait, door)?;
+        }
 
-    door_lockfile.unlock()?;
+        let mut run = config.run_container(&env, &volumes, &labels, &door.options);
 
-    run.wait()
-        .with_context(|| format!("While waiting for container for door '{}'", door.name))?;
+        if interactive {
+            run.arg("-ti");
+        } else {
+            let mut log = fs::File::options()
+                .append(true)
+                .open(&log_path)
+                .with_context(|| format!("Couldn't open log {}", log_path.display()))?;
+
+            writeln!(
+                log,
+                "*** {} attempt {} of {} ***",
+                command, attempt, attempts
+            )?;
+
+            run.stdin(Stdio::null())
+                .stdout(log.try_clone()?)
+                .stderr(log);
+        }
+
+        let mut run = run
+            .arg(config.door_image(door))
+            // Warm-up runs headless, the same way nightly maintenence does
+            .arg(match command {
+                "warmup" => "nightly.sh".to_string(),
+                _ => format!("{}.sh", command),
+            })
+            .spawn()
+            .with_context(|| format!("While spawning container for door '{}'", door.name))?;
+
+        door_lockfile.unlock()?;
+
+        let status = run
+            .wait()
+            .with_context(|| format!("While waiting for container for door '{}'", door.name))?;
+
+        if status.success() {
+            break;
+        }
+
+        warn!(
+            "{} for {} failed with {} (attempt {} of {})",
+            command, door.name, status, attempt, attempts
+        );
+
+        if attempt >= attempts {
+            if command == "nightly" {
+                stats::record_nightly(config, door, false)?;
+            }
+
+            return Err(if interactive {
+                anyhow!("{} for {} failed with {}", command, door.name, status)
+            } else {
+                anyhow!(
+                    "{} for {} failed with {}; its output is in {}",
+                    command,
+                    door.name,
+                    status,
+                    log_path.display()
+                )
+            });
+        }
+
+        attempt += 1;
+        thread::sleep(Duration::from_secs(door.options.nightly_retry_delay));
+    }
+
+    if command == "nightly" {
+        stats::record_nightly(config, door, true)?;
+
+        // Written once maintenence is done with the door's files
+        if let Err(err) = stats::write_door_stats(config, door, &templates) {
+            warn!("{:#}", err);
+        }
+
+        if let Err(err) = bulletin::write(config) {
+            warn!("{:#}", err);
+        }
+    }
 
     Ok(())
 }
@@ -1,50 +1,314 @@
 use anyhow::{anyhow, Context, Result};
-use handlebars::Handlebars;
+use chrono::{DateTime, Local, NaiveDate};
+use handlebars::{handlebars_helper, Handlebars, JsonValue};
+use log::debug;
 use rust_embed::RustEmbed;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
-use std::path::Path;
-use yore::code_pages::CP437;
+use std::path::{Path, PathBuf};
+use yore::code_pages::{
+    CP437, CP737, CP850, CP852, CP855, CP857, CP860, CP861, CP862, CP863, CP865, CP866, CP869,
+};
+use yore::CodePage;
 
 #[derive(RustEmbed)]
 #[folder = "$CARGO_MANIFEST_DIR/templates/dos"]
 struct Asset;
 
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropFile {
+    #[serde(rename = "door.sys", alias = "DOOR.SYS")]
+    DoorSys,
+
+    #[serde(rename = "door32.sys", alias = "DOOR32.SYS")]
+    Door32Sys,
+
+    #[serde(rename = "dorinfo1.def", alias = "DORINFO1.DEF")]
+    Dorinfo1Def,
+
+    #[serde(rename = "dorinfox.def", alias = "DORINFOX.DEF")]
+    DorinfoxDef,
+
+    #[serde(rename = "chain.txt", alias = "CHAIN.TXT")]
+    ChainTxt,
+
+    #[serde(rename = "sfdoors.dat", alias = "SFDOORS.DAT")]
+    SfdoorsDat,
+}
+impl DropFile {
+    pub fn template(&self) -> &'static str {
+        match self {
+            DropFile::DoorSys => "door.sys",
+            DropFile::Door32Sys => "door32.sys",
+            DropFile::Dorinfo1Def | DropFile::DorinfoxDef => "dorinfo1.def",
+            DropFile::ChainTxt => "chain.txt",
+            DropFile::SfdoorsDat => "sfdoors.dat",
+        }
+    }
+
+    pub fn filename(&self, node: i8) -> Result<String> {
+        if *self != DropFile::DorinfoxDef {
+            return Ok(self.template().to_uppercase());
+        }
+
+        let digit = u32::try_from(node)
+            .ok()
+            .and_then(|node| char::from_digit(node, 36))
+            .ok_or(anyhow!("Can't name a DORINFO file for node {}", node))?;
+
+        Ok(format!("DORINFO{}.DEF", digit.to_ascii_uppercase()))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Codepage {
+    #[default]
+    Cp437,
+    Cp737,
+    Cp850,
+    Cp852,
+    Cp855,
+    Cp857,
+    Cp860,
+    Cp861,
+    Cp862,
+    Cp863,
+    Cp865,
+    Cp866,
+    Cp869,
+}
+impl Codepage {
+    fn encoder(&self) -> &'static dyn CodePage {
+        match self {
+            Codepage::Cp437 => &CP437,
+            Codepage::Cp737 => &CP737,
+            Codepage::Cp850 => &CP850,
+            Codepage::Cp852 => &CP852,
+            Codepage::Cp855 => &CP855,
+            Codepage::Cp857 => &CP857,
+            Codepage::Cp860 => &CP860,
+            Codepage::Cp861 => &CP861,
+            Codepage::Cp862 => &CP862,
+            Codepage::Cp863 => &CP863,
+            Codepage::Cp865 => &CP865,
+            Codepage::Cp866 => &CP866,
+            Codepage::Cp869 => &CP869,
+        }
+    }
+
+    /// Encodes text in this code page, with ? for anything it can't represent
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        self.encoder().encode_lossy(text, 63).into_owned()
+    }
+
+    /// Decodes bytes in this code page, with U+FFFD for anything undefined
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        self.encoder().decode_lossy(bytes).into_owned()
+    }
+}
+
+pub fn default_dropfiles() -> Vec<DropFile> {
+    vec![
+        DropFile::DoorSys,
+        DropFile::Door32Sys,
+        DropFile::Dorinfo1Def,
+        DropFile::ChainTxt,
+    ]
+}
+
+fn display_value(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(string) => string.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn format_datetime(value: Option<&&JsonValue>, format: &str) -> String {
+    let datetime = match value {
+        Some(JsonValue::Number(timestamp)) => timestamp
+            .as_i64()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0))
+            .map(DateTime::<Local>::from),
+        Some(JsonValue::String(string)) => DateTime::parse_from_rfc3339(string)
+            .map(DateTime::<Local>::from)
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(string, "%Y-%m-%d")
+                    .ok()
+                    .and_then(|date| date.and_hms_opt(0, 0, 0))
+                    .and_then(|datetime| datetime.and_local_timezone(Local).single())
+            }),
+        _ => Some(Local::now()),
+    };
+
+    datetime.map_or(String::new(), |datetime| {
+        datetime.format(format).to_string()
+    })
+}
+
+// {{upper value}}
+handlebars_helper!(upper: |value: Json| display_value(value).to_uppercase());
+
+// {{truncate value width}}
+handlebars_helper!(truncate: |value: Json, width: u64| {
+    display_value(value).chars().take(width as usize).collect::<String>()
+});
+
+// {{pad value width}} left-justifies value in a field of width spaces
+handlebars_helper!(pad: |value: Json, width: u64| {
+    format!("{:<width$}", display_value(value), width = width as usize)
+});
+
+// {{padl value width}} right-justifies value in a field of width spaces
+handlebars_helper!(padl: |value: Json, width: u64| {
+    format!("{:>width$}", display_value(value), width = width as usize)
+});
+
+// {{dosdate}} or {{dosdate timestamp}} renders MM/DD/YY
+handlebars_helper!(dosdate: |*args| format_datetime(args.first(), "%m/%d/%y"));
+
+// {{dostime}} or {{dostime timestamp}} renders HH:MM
+handlebars_helper!(dostime: |*args| format_datetime(args.first(), "%H:%M"));
+
+// {{ansi "1;33"}} renders an ANSI color escape
+handlebars_helper!(ansi: |codes: str| format!("\x1b[{}m", codes));
+
+const DOS_FILENAME_PUNCTUATION: &str = "!#$%&'()-@^_`{}~";
+
+fn is_dos_filename_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || DOS_FILENAME_PUNCTUATION.contains(c)
+}
+
+/// Checks that filename is a valid DOS 8.3 name, or squeezes it into one if shorten is set
+pub fn dos_filename(filename: &str, shorten: bool) -> Result<String> {
+    let name = filename.to_uppercase();
+    let (base, ext) = name.rsplit_once('.').unwrap_or((&name, ""));
+
+    if shorten {
+        let base: String = base
+            .chars()
+            .filter(|c| is_dos_filename_char(*c))
+            .take(8)
+            .collect();
+        let ext: String = ext
+            .chars()
+            .filter(|c| is_dos_filename_char(*c))
+            .take(3)
+            .collect();
+
+        if base.is_empty() {
+            return Err(anyhow!("Can't make a DOS filename out of '{}'", filename));
+        }
+
+        return Ok(if ext.is_empty() {
+            base
+        } else {
+            format!("{}.{}", base, ext)
+        });
+    }
+
+    if let Some(bad) = name
+        .chars()
+        .find(|c| *c != '.' && !is_dos_filename_char(*c))
+    {
+        return Err(anyhow!(
+            "'{}' isn't a valid DOS filename: '{}' isn't allowed",
+            filename,
+            bad
+        ));
+    }
+
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 || base.contains('.') {
+        return Err(anyhow!(
+            "'{}' isn't a valid DOS filename: names must fit in 8.3 characters",
+            filename
+        ));
+    }
+
+    Ok(name)
+}
+
 pub struct Templates<'a> {
     hbars: Handlebars<'a>,
+    template_dir: PathBuf,
+    codepage: Codepage,
+    shorten_filenames: bool,
 }
 
 impl Templates<'_> {
-    pub fn new() -> Templates<'static> {
+    /// Templates in template_dir take precedence over the built-in ones
+    pub fn new(template_dir: &Path, codepage: Codepage) -> Templates<'static> {
         let mut hbars = Handlebars::new();
         hbars.register_escape_fn(handlebars::no_escape);
+        hbars.register_helper("upper", Box::new(upper));
+        hbars.register_helper("truncate", Box::new(truncate));
+        hbars.register_helper("pad", Box::new(pad));
+        hbars.register_helper("padl", Box::new(padl));
+        hbars.register_helper("dosdate", Box::new(dosdate));
+        hbars.register_helper("dostime", Box::new(dostime));
+        hbars.register_helper("ansi", Box::new(ansi));
 
-        Templates { hbars }
+        Templates {
+            hbars,
+            template_dir: template_dir.to_path_buf(),
+            codepage,
+            shorten_filenames: false,
+        }
     }
 
-    pub fn render_string<T: Serialize>(&self, template: &str, vars: T) -> Result<String> {
-        Ok(self.hbars.render_template(template, &vars)?)
+    /// Shorten filenames that don't fit in 8.3 characters instead of failing
+    pub fn shorten_filenames(mut self, shorten_filenames: bool) -> Self {
+        self.shorten_filenames = shorten_filenames;
+        self
     }
 
-    pub fn render_template<T: Serialize>(&self, name: &str, vars: T) -> Result<String> {
-        if let Some(asset) = Asset::get(format!("{0}.hbr", name).as_str()) {
-            let template = String::from_utf8(asset.data.to_vec())
-                .with_context(|| format!("While converting template {} to UTF-8", name))?;
+    fn load_template(&self, name: &str) -> Result<String> {
+        let filename = format!("{0}.hbr", name);
+        let path = self.template_dir.join(&filename);
 
-            return self
-                .render_string(&template, &vars)
-                .with_context(|| format!("While rendirng template {}", name));
+        if path.exists() {
+            debug!("Using template {}", path.display());
+            return fs::read_to_string(&path)
+                .with_context(|| format!("Couldn't read template {}", path.display()));
+        }
+
+        if let Some(asset) = Asset::get(&filename) {
+            return String::from_utf8(asset.data.to_vec())
+                .with_context(|| format!("While converting template {} to UTF-8", name));
         }
 
         Err(anyhow!("Couldn't find template for {0}", name))
     }
 
+    pub fn render_string<T: Serialize>(&self, template: &str, vars: T) -> Result<String> {
+        Ok(self.hbars.render_template(template, &vars)?)
+    }
+
+    pub fn render_template<T: Serialize>(&self, name: &str, vars: T) -> Result<String> {
+        let template = self.load_template(name)?;
+
+        self.render_string(&template, &vars)
+            .with_context(|| format!("While rendirng template {}", name))
+    }
+
     pub fn write_dos<T: Serialize>(&self, name: &str, dir: &Path, vars: T) -> Result<()> {
+        self.write_dos_as(name, &name.to_uppercase(), dir, vars)
+    }
+
+    pub fn write_dos_as<T: Serialize>(
+        &self,
+        name: &str,
+        filename: &str,
+        dir: &Path,
+        vars: T,
+    ) -> Result<()> {
         let rendered = self.render_template(name, vars)?;
         let crlf = rendered.replace('\n', "\r\n");
-        let encoded = CP437.encode_lossy(&crlf, 63);
-        let path = dir.join(name.to_uppercase());
+        let encoded = self.codepage.encode(&crlf);
+        let path = dir.join(dos_filename(filename, self.shorten_filenames)?);
 
         let mut output = fs::File::create(path)?;
         output.write_all(&encoded)?;
@@ -0,0 +1,454 @@
+use super::config;
+use super::menu;
+use super::{ConfigEditArgs, ConfigRollbackArgs};
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use serde_yaml::{Mapping, Value};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const NEW_DOOR: &str = "door_path: /path/to/door\nmax_nodes: 1\nlaunch_commands: DOOR.EXE\n";
+
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or("vi".to_string());
+
+    // Go through the shell so EDITOR can have arguments, i.e. "code --wait"
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("sh")
+        .arg(path)
+        .status()
+        .with_context(|| format!("Couldn't run editor '{}'", editor))?;
+
+    if !status.success() {
+        return Err(anyhow!("Editor '{}' exited with {}", editor, status));
+    }
+
+    Ok(())
+}
+
+/// How far a line is indented
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Whether a line continues the top-level section above it
+fn in_section(line: &str) -> bool {
+    line.trim().is_empty() || line.starts_with([' ', '\t'])
+}
+
+/// Whether a line is blank or only a comment
+fn is_filler(line: &str) -> bool {
+    let line = line.trim();
+
+    line.is_empty() || line.starts_with('#')
+}
+
+/// Where a door's entry is in the doors section, as lines
+struct DoorLines {
+    /// The line the door's name is on
+    header: usize,
+
+    /// The line after the door's last one
+    end: usize,
+
+    /// How far the door's settings are indented
+    indent: usize,
+}
+
+/// The lines of the doors section: the doors: line, and the line after the section's
+/// last entry, not counting trailing blank lines
+fn doors_lines(lines: &[String]) -> Option<(usize, usize)> {
+    let start = lines.iter().position(|line| {
+        line.strip_prefix("doors:").is_some_and(|rest| {
+            let rest = rest.trim();
+            rest.is_empty() || rest == "{}" || rest.starts_with('#')
+        })
+    })?;
+
+    let mut end = start + 1;
+
+    while end < lines.len() && in_section(&lines[end]) {
+        end += 1;
+    }
+
+    while end > start + 1 && lines[end - 1].trim().is_empty() {
+        end -= 1;
+    }
+
+    Some((start, end))
+}
+
+/// Finds door's entry in the doors section between start and end, if it's written as
+/// a plain block mapping
+fn door_lines(lines: &[String], start: usize, end: usize, door: &str) -> Option<DoorLines> {
+    let entry_indent = indent(lines[start + 1..end].iter().find(|line| !is_filler(line))?);
+
+    let header = (start + 1..end).find(|&index| {
+        indent(&lines[index]) == entry_indent
+            && lines[index]
+                .trim()
+                .strip_prefix(door)
+                .and_then(|rest| rest.strip_prefix(':'))
+                .is_some_and(|rest| {
+                    let rest = rest.trim();
+                    rest.is_empty() || rest.starts_with('#')
+                })
+    })?;
+
+    let mut door_end = header + 1;
+
+    while door_end < end
+        && (lines[door_end].trim().is_empty() || indent(&lines[door_end]) > entry_indent)
+    {
+        door_end += 1;
+    }
+
+    while door_end > header + 1 && lines[door_end - 1].trim().is_empty() {
+        door_end -= 1;
+    }
+
+    let settings_indent = lines[header + 1..door_end]
+        .iter()
+        .find(|line| !is_filler(line))
+        .map_or(entry_indent + 2, |line| indent(line));
+
+    Some(DoorLines {
+        header,
+        end: door_end,
+        indent: settings_indent,
+    })
+}
+
+/// The text of door's settings to edit, as written in the config so comments come along;
+/// a skeleton if it's a new door
+fn door_section(original: &str, door: &str) -> Result<String> {
+    let document: Value = serde_yaml::from_str(original)?;
+
+    let Some(section) = document.get("doors").and_then(|doors| doors.get(door)) else {
+        return Ok(NEW_DOOR.to_string());
+    };
+
+    let lines: Vec<String> = original.lines().map(String::from).collect();
+    let found = doors_lines(&lines).and_then(|(start, end)| door_lines(&lines, start, end, door));
+
+    let Some(found) = found else {
+        return Ok(serde_yaml::to_string(section)?);
+    };
+
+    let mut text: String = lines[found.header + 1..found.end]
+        .iter()
+        .map(|line| {
+            let strip = indent(line).min(found.indent);
+            format!("{}\n", &line[strip..])
+        })
+        .collect();
+
+    if text.trim().is_empty() {
+        text = serde_yaml::to_string(section)?;
+    }
+
+    Ok(text)
+}
+
+/// Replaces door's settings with edited, or adds the door, working on the text so
+/// comments elsewhere in the config survive
+fn splice_door_text(original: &str, door: &str, edited: &str) -> Option<String> {
+    let mut lines: Vec<String> = original.lines().map(String::from).collect();
+    let (start, mut end) = doors_lines(&lines)?;
+
+    lines[start] = lines[start].replacen("{}", "", 1).trim_end().to_string();
+
+    // An empty flow mapping can't be mixed with block entries
+    for index in (start + 1..end).rev() {
+        if lines[index].trim() == "{}" {
+            lines.remove(index);
+            end -= 1;
+        }
+    }
+
+    let reindent = |settings_indent: usize| -> Vec<String> {
+        edited
+            .lines()
+            .map(|line| match line.trim().is_empty() {
+                true => String::new(),
+                false => format!("{}{}", " ".repeat(settings_indent), line),
+            })
+            .collect()
+    };
+
+    match door_lines(&lines, start, end, door) {
+        Some(found) => {
+            lines.splice(found.header + 1..found.end, reindent(found.indent));
+        }
+        None => {
+            let entry_indent = lines[start + 1..end]
+                .iter()
+                .find(|line| !is_filler(line))
+                .map_or(2, |line| indent(line));
+            let mut block = vec![format!("{}{}:", " ".repeat(entry_indent), door)];
+
+            block.extend(reindent(entry_indent + 2));
+            lines.splice(end..end, block);
+        }
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+
+    Some(updated)
+}
+
+/// Replaces door's settings with edited, or adds the door. The text is spliced in
+/// where possible, so comments survive; if the config is laid out in a way this
+/// doesn't understand, the whole config is rewritten instead.
+fn splice_door(original: &str, door: &str, edited: &str) -> Result<String> {
+    let mut document: Value = serde_yaml::from_str(original)?;
+    let section: Value =
+        serde_yaml::from_str(edited).with_context(|| format!("Couldn't parse door '{}'", door))?;
+
+    let mapping = document
+        .as_mapping_mut()
+        .ok_or(anyhow!("Config file isn't a mapping"))?;
+
+    let doors = mapping
+        .entry(Value::from("doors"))
+        .or_insert(Value::Mapping(Mapping::new()));
+
+    doors
+        .as_mapping_mut()
+        .ok_or(anyhow!("'doors' isn't a mapping"))?
+        .insert(Value::from(door), section);
+
+    // Only trust the text if it means the same thing as the rewritten document
+    if let Some(updated) = splice_door_text(original, door, edited) {
+        if serde_yaml::from_str::<Value>(&updated).is_ok_and(|spliced| spliced == document) {
+            return Ok(updated);
+        }
+    }
+
+    eprintln!(
+        "WARNING: couldn't find where {} goes in the config, so its comments will be lost",
+        door
+    );
+
+    Ok(serde_yaml::to_string(&document)?)
+}
+
+/// Adds a door to the config, keeping comments
+pub fn add_door(original: &str, door: &str, section: &Mapping) -> Result<String> {
+    splice_door(original, door, &serde_yaml::to_string(section)?)
+}
+
+fn extension(config_path: &Path) -> &str {
+    config_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("yml")
+}
+
+fn history_dir(config_path: &Path) -> PathBuf {
+    config_path
+        .parent()
+        .map_or(PathBuf::from("history"), |dir| dir.join("history"))
+}
+
+/// Saved versions of the config, newest first
+fn history(config_path: &Path) -> Result<Vec<PathBuf>> {
+    let dir = history_dir(config_path);
+
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut versions: Vec<PathBuf> = fs::read_dir(&dir)
+        .with_context(|| format!("Couldn't read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        // Backups keep the config's extension, whatever it is
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("doorman-"))
+        })
+        .collect();
+
+    versions.sort();
+    versions.reverse();
+
+    Ok(versions)
+}
+
+/// Replaces the config file with contents, keeping the previous version in the history
+pub fn write_config(config_path: &Path, contents: &str) -> Result<PathBuf> {
+    let dir = history_dir(config_path);
+    let backup_path = dir.join(format!(
+        "doorman-{}.{}",
+        Local::now().format("%Y%m%dT%H%M%S%.3f"),
+        extension(config_path)
+    ));
+    let temp_path = config_path.with_extension(format!("{}.tmp", extension(config_path)));
+
+    fs::create_dir_all(&dir).with_context(|| format!("Couldn't create {}", dir.display()))?;
+    fs::copy(config_path, &backup_path)
+        .with_context(|| format!("Couldn't back up config to {}", backup_path.display()))?;
+
+    let mut temp = fs::File::create(&temp_path)
+        .with_context(|| format!("Couldn't create {}", temp_path.display()))?;
+
+    temp.write_all(contents.as_bytes())?;
+    temp.sync_all()?;
+    temp.set_permissions(fs::metadata(config_path)?.permissions())?;
+
+    fs::rename(&temp_path, config_path)
+        .with_context(|| format!("Couldn't replace {}", config_path.display()))?;
+
+    Ok(backup_path)
+}
+
+pub fn edit_command(args: &ConfigEditArgs, config: &config::Config) -> Result<()> {
+    if !config.is_sysop() {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let config_path = config::config_path();
+
+    if args.door.is_some() && config::is_toml(&config_path) {
+        return Err(anyhow!(
+            "Editing a single door only works with YAML configs; use 'doorman config edit' to edit all of {}",
+            config_path.display()
+        ));
+    }
+
+    if let Some(door) = &args.door {
+        if let Some(path) = config::included_door_file(door)? {
+            return Err(anyhow!(
+                "{} is defined in {}; edit that file instead",
+                door,
+                path.display()
+            ));
+        }
+    }
+
+    let original = fs::read_to_string(&config_path)
+        .with_context(|| format!("Couldn't read config file: {}", config_path.display()))?;
+
+    let section = match &args.door {
+        Some(door) => door_section(&original, door)?,
+        None => original.clone(),
+    };
+
+    // Created exclusively and only readable by us, since the config can hold secrets
+    let mut edit_file = tempfile::Builder::new()
+        .prefix("doorman-")
+        .suffix(&format!(".{}", extension(&config_path)))
+        .tempfile()
+        .with_context(|| "Couldn't create a file to edit")?;
+    let edit_path = edit_file.path().to_path_buf();
+
+    edit_file
+        .write_all(section.as_bytes())
+        .with_context(|| format!("Couldn't write {}", edit_path.display()))?;
+
+    let result = loop {
+        run_editor(&edit_path)?;
+
+        let edited = fs::read_to_string(&edit_path)?;
+
+        if edited == section {
+            break None;
+        }
+
+        let updated = match &args.door {
+            Some(door) => splice_door(&original, door, &edited),
+            None => Ok(edited),
+        }
+        .and_then(|updated| config::validate(&config_path, &updated).map(|_| updated));
+
+        match updated {
+            Ok(updated) => break Some(updated),
+            Err(err) => {
+                eprintln!("That config isn't valid: {:#}", err);
+
+                if !menu::confirm("Edit it again?")? {
+                    break None;
+                }
+            }
+        }
+    };
+
+    drop(edit_file);
+
+    match result {
+        Some(updated) => {
+            let backup_path = write_config(&config_path, &updated)?;
+
+            println!(
+                "Saved {}; the previous version is in {}",
+                config_path.display(),
+                backup_path.display()
+            );
+        }
+        None => println!("No changes made."),
+    }
+
+    Ok(())
+}
+
+pub fn history_command(config: &config::Config) -> Result<()> {
+    if !config.is_sysop() {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let versions = history(&config::config_path())?;
+
+    if versions.is_empty() {
+        println!("No previous versions of the config have been saved.");
+        return Ok(());
+    }
+
+    for (index, version) in versions.iter().enumerate() {
+        println!("{:>3}  {}", index + 1, version.display());
+    }
+
+    Ok(())
+}
+
+pub fn rollback_command(args: &ConfigRollbackArgs, config: &config::Config) -> Result<()> {
+    if !config.is_sysop() {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let config_path = config::config_path();
+    let versions = history(&config_path)?;
+
+    let version = versions
+        .get(args.number.saturating_sub(1))
+        .ok_or(anyhow!("There's no saved config number {}", args.number))?;
+
+    let contents = fs::read_to_string(version)
+        .with_context(|| format!("Couldn't read {}", version.display()))?;
+
+    if let Err(err) = config::validate(&config_path, &contents) {
+        eprintln!("Warning: {} isn't valid: {:#}", version.display(), err);
+
+        if !menu::confirm("Restore it anyway?")? {
+            return Ok(());
+        }
+    }
+
+    let backup_path = write_config(&config_path, &contents)?;
+
+    println!(
+        "Restored {}; the config it replaced is in {}",
+        version.display(),
+        backup_path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,202 @@
+use super::config::{self, Capability};
+use super::who;
+use super::GcArgs;
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use log::{debug, warn};
+use std::process::Command;
+
+fn run_output(cmd: &mut Command) -> Result<String> {
+    let output = cmd.output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!("{:?} exited with status {}", cmd, output.status));
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn image_repository(image: &str) -> &str {
+    let image = image.split('@').next().unwrap_or(image);
+
+    match image.rfind(':') {
+        Some(colon) if !image[colon..].contains('/') => &image[..colon],
+        _ => image,
+    }
+}
+
+fn normalize_image_id(id: &str) -> &str {
+    id.trim().trim_start_matches("sha256:")
+}
+
+/// Doorman containers that have exited, or were created and never started
+pub fn exited_containers(config: &config::Config) -> Result<Vec<who::WhoNode>> {
+    let stdout = run_output(
+        config
+            .ps_command()
+            .arg("--all")
+            .arg("--filter=label=doorman.door")
+            .arg("--filter=status=exited")
+            .arg("--filter=status=created"),
+    )?;
+
+    Ok(who::parse_ps(&stdout))
+}
+
+fn stale_containers(args: &GcArgs, config: &config::Config) -> Result<Vec<who::WhoNode>> {
+    let cutoff = Utc::now() - Duration::hours(args.older_than);
+
+    Ok(exited_containers(config)?
+        .into_iter()
+        .filter(|node| node.since <= cutoff)
+        .collect())
+}
+
+pub fn remove_containers(config: &config::Config, containers: &[who::WhoNode]) -> Result<()> {
+    if containers.is_empty() {
+        return Ok(());
+    }
+
+    // -v takes any anonymous volumes the container created along with it
+    let mut rm = config.container_command("rm");
+
+    rm.arg("-v");
+
+    for container in containers.iter() {
+        rm.arg(&container.container_id);
+    }
+
+    run_output(&mut rm)?;
+
+    Ok(())
+}
+
+fn stale_images(config: &config::Config) -> Result<Vec<(String, String)>> {
+    let images = config.images();
+    let repositories: Vec<&str> = images.iter().map(|image| image_repository(image)).collect();
+
+    // Only the tags doors run in now are kept; older ones in the same repositories go
+    let current: Vec<String> = images
+        .iter()
+        .map(|image| {
+            run_output(
+                config
+                    .container_command("image")
+                    .arg("inspect")
+                    .arg("--format={{.Id}}")
+                    .arg(image),
+            )
+            .unwrap_or_default()
+        })
+        .map(|id| normalize_image_id(&id).to_string())
+        .filter(|id| !id.is_empty())
+        .collect();
+
+    debug!("Image IDs in use: {:?}", current);
+
+    let stdout = run_output(
+        config
+            .container_command("images")
+            .arg("--format={{.ID}} {{.Repository}}:{{.Tag}}"),
+    )?;
+
+    let mut stale: Vec<(String, String)> = vec![];
+
+    for line in stdout.lines() {
+        let (id, name) = line.split_once(' ').unwrap_or((line, ""));
+        let id = normalize_image_id(id);
+
+        if !repositories.contains(&image_repository(name)) {
+            continue;
+        }
+
+        if current
+            .iter()
+            .any(|current| current.starts_with(id) || id.starts_with(current.as_str()))
+        {
+            continue;
+        }
+
+        if stale.iter().any(|(seen, _)| seen == id) {
+            continue;
+        }
+
+        stale.push((id.to_string(), name.to_string()));
+    }
+
+    Ok(stale)
+}
+
+/// Doorman volumes no container uses anymore
+fn stale_volumes(config: &config::Config) -> Result<Vec<String>> {
+    let stdout = run_output(
+        config
+            .container_command("volume")
+            .arg("ls")
+            .arg("--quiet")
+            .arg("--filter=dangling=true")
+            .arg("--filter=label=doorman.door"),
+    )?;
+
+    Ok(stdout
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+pub fn gc_command(args: &GcArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Nightly) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let containers = stale_containers(args, config)?;
+    let images = stale_images(config)?;
+    let volumes = stale_volumes(config)?;
+
+    if containers.is_empty() && images.is_empty() && volumes.is_empty() {
+        println!("Nothing to clean up.");
+        return Ok(());
+    }
+
+    let verb = if args.dry_run {
+        "Would remove"
+    } else {
+        "Removing"
+    };
+
+    for container in containers.iter() {
+        println!(
+            "{} container {} ({} on {})",
+            verb, container.container_id, container.user, container.door
+        );
+    }
+
+    for (id, name) in images.iter() {
+        println!("{} image {} ({})", verb, id, name);
+    }
+
+    for volume in volumes.iter() {
+        println!("{} volume {}", verb, volume);
+    }
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    remove_containers(config, &containers)?;
+
+    for (id, name) in images.iter() {
+        if let Err(err) = run_output(config.container_command("rmi").arg(id)) {
+            warn!("Couldn't remove image {} ({}): {:#}", id, name, err);
+        }
+    }
+
+    for volume in volumes.iter() {
+        if let Err(err) = run_output(config.container_command("volume").arg("rm").arg(volume)) {
+            warn!("Couldn't remove volume {}: {:#}", volume, err);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use super::audit;
+use super::config;
+use super::menu;
+use super::user::User;
+use super::who;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+/// How many times a guest gets to pick a handle before doorman gives up on them
+const TRIES: usize = 3;
+
+/// A shared account, i.e. one exposed over public telnet or ssh, whose callers each
+/// pick a handle and play as a guest
+#[derive(Deserialize, Debug, Clone)]
+pub struct GuestOptions {
+    /// The Unix account guests come in through
+    pub account: String,
+
+    /// Where to keep guests' history, audit log, launch counts and play statistics,
+    /// instead of doorman's datadir; defaults to a guest directory inside it
+    pub datadir: Option<PathBuf>,
+
+    #[serde(default)]
+    /// The only doors guests may play; if empty, they may play any door that's
+    /// open to everyone
+    pub doors: Vec<String>,
+
+    /// Minutes a guest session may last; shorter limits elsewhere still apply
+    pub time_limit: Option<u32>,
+
+    /// What to ask guests when they arrive
+    pub prompt: Option<String>,
+}
+
+/// The part of a handle that goes into a guest's username: its letters and digits,
+/// in lowercase
+fn slug(handle: &str) -> String {
+    handle
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Makes a user out of handle, if nobody else is playing under it
+fn guest_user(config: &config::Config, guest: &GuestOptions, handle: &str) -> Result<User> {
+    let slug = slug(handle);
+
+    if slug.is_empty() {
+        return Err(anyhow!(
+            "Your handle needs at least one letter or number in it."
+        ));
+    }
+
+    let mut user = User {
+        uid: config.user.uid,
+        username: format!("{}-{}", guest.account, slug),
+        display_name: handle.to_string(),
+    };
+
+    // Sharing a username would let guests rejoin each other's sessions, including
+    // ones whose callers have dropped
+    if who::who(&None, config)?
+        .iter()
+        .any(|session| session.user == user.username)
+    {
+        return Err(anyhow!(
+            "Someone is already playing as {}; please pick another handle.",
+            handle
+        ));
+    }
+
+    user.display_name = config.display_names.apply(&user)?;
+
+    Ok(user)
+}
+
+/// Asks a guest for a handle and makes them a user of their own, named after it
+pub fn sign_in(config: &mut config::Config) -> Result<()> {
+    let Some(guest) = config.guest.clone() else {
+        return Ok(());
+    };
+
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!("Guests need a terminal to pick a handle"));
+    }
+
+    for _ in 0..TRIES {
+        let handle = menu::prompt(
+            guest
+                .prompt
+                .as_deref()
+                .unwrap_or("What handle would you like to play under?"),
+        )?;
+
+        match guest_user(config, &guest, &handle) {
+            Ok(user) => {
+                let detail = format!("{} as {}", user.username, user.display_name);
+
+                config.user = user;
+                audit::record(config, "guest", None, None, Some(detail));
+
+                return Ok(());
+            }
+            Err(err) => println!("{:#}", err),
+        }
+    }
+
+    Err(anyhow!("Too many tries; goodbye!"))
+}
@@ -0,0 +1,143 @@
+use super::charset;
+use super::config::{self, Capability};
+use super::list;
+use super::{HistoryArgs, OutputFormat};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use comfy_table::Cell;
+use fs4::FileExt;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+/// A finished session, as kept in datadir/history.jsonl
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HistoryEntry {
+    pub user: String,
+    pub display_name: Option<String>,
+    pub door: String,
+    pub node: i8,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+
+    /// launch.sh's exit code; None if it was killed
+    pub exit_code: Option<i32>,
+}
+
+fn history_path(config: &config::Config) -> PathBuf {
+    config.datadir.join("history.jsonl")
+}
+
+/// Appends a session to the history
+pub fn record(config: &config::Config, entry: &HistoryEntry) -> Result<()> {
+    let path = history_path(config);
+    let mut file = fs::File::options()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Couldn't open history {}", path.display()))?;
+
+    file.lock_exclusive()?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+
+    Ok(())
+}
+
+/// Every session in the history, oldest first
+pub fn read(config: &config::Config) -> Result<Vec<HistoryEntry>> {
+    let path = history_path(config);
+
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let file = fs::File::open(&path)
+        .with_context(|| format!("Couldn't open history {}", path.display()))?;
+
+    file.lock_shared()?;
+
+    let mut entries: Vec<HistoryEntry> = vec![];
+
+    for line in BufReader::new(file).lines() {
+        match serde_json::from_str(&line?) {
+            Ok(entry) => entries.push(entry),
+            Err(err) => debug!("Skipping bad history line: {}", err),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn duration(entry: &HistoryEntry) -> String {
+    HumanTime::from(entry.end.signed_duration_since(entry.start))
+        .to_text_en(Accuracy::Rough, Tense::Present)
+}
+
+pub fn history_command(args: &HistoryArgs, config: &config::Config) -> Result<()> {
+    // Without the stats capability, people only get to see their own sessions
+    let user = if config.can(Capability::Stats) {
+        args.user.clone()
+    } else {
+        Some(config.user.username.clone())
+    };
+
+    let door = match &args.door {
+        Some(door) => Some(config.get_door(door)?.name),
+        None => None,
+    };
+
+    let entries: Vec<HistoryEntry> = read(config)?
+        .into_iter()
+        .rev()
+        .filter(|entry| user.as_ref().is_none_or(|user| &entry.user == user))
+        .filter(|entry| door.as_ref().is_none_or(|door| &entry.door == door))
+        .take(args.count)
+        .collect();
+
+    if let Some(format) = &args.format {
+        println!(
+            "{}",
+            match format {
+                OutputFormat::Json => serde_json::to_string(&entries)?,
+                OutputFormat::Yaml => serde_yaml::to_string(&entries)?,
+                OutputFormat::Csv => list::to_csv(&entries)?,
+            }
+        );
+
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("No sessions found.");
+        return Ok(());
+    }
+
+    let charset = charset::detect(None);
+    let mut table = charset::new_table(charset);
+
+    table.set_header(vec!["User", "Door", "Node", "Started", "Duration", "Exit"]);
+
+    for entry in entries.iter() {
+        table.add_row(vec![
+            Cell::new(&entry.user),
+            Cell::new(&entry.door),
+            Cell::new(entry.node),
+            Cell::new(
+                DateTime::<Local>::from(entry.start)
+                    .format("%F %H:%M")
+                    .to_string(),
+            ),
+            Cell::new(duration(entry)),
+            Cell::new(
+                entry
+                    .exit_code
+                    .map_or("-".to_string(), |code| code.to_string()),
+            ),
+        ]);
+    }
+
+    charset::print_line(charset, &table.to_string())
+}
@@ -0,0 +1,106 @@
+use super::config::{self, Door};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Host-side programs to run around a session, for everything doorman doesn't do itself
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Hooks {
+    #[serde(default)]
+    /// Run before the door's container starts, after drop files are written; a
+    /// non-zero exit turns the player away
+    pub pre_launch: Vec<PathBuf>,
+
+    #[serde(default)]
+    /// Run after the door's container exits, not when a player just detaches;
+    /// failures are only logged
+    pub post_exit: Vec<PathBuf>,
+}
+
+/// What hooks get told about the session, in DOORMAN_* environment variables
+pub struct HookSession<'a> {
+    pub door: &'a Door,
+    pub node: i8,
+    pub rundir: &'a Path,
+    pub container_id: Option<&'a str>,
+    pub exit_code: Option<i32>,
+}
+
+fn run_hook(
+    config: &config::Config,
+    hook: &str,
+    program: &Path,
+    session: &HookSession,
+) -> Result<()> {
+    let mut cmd = Command::new(program);
+
+    cmd.env("DOORMAN_HOOK", hook)
+        .env("DOORMAN_DOOR", &session.door.name)
+        .env("DOORMAN_NODE", session.node.to_string())
+        .env("DOORMAN_USER", &config.user.username)
+        .env("DOORMAN_USER_ID", config.user.uid.to_string())
+        .env("DOORMAN_DISPLAY_NAME", &config.user.display_name)
+        .env("DOORMAN_RUNDIR", session.rundir)
+        .env("DOORMAN_DOOR_PATH", &session.door.options.door_path);
+
+    if let Some(impersonated_by) = &config.impersonated_by {
+        cmd.env("DOORMAN_IMPERSONATED_BY", impersonated_by);
+    }
+
+    if let Some(container_id) = session.container_id {
+        cmd.env("DOORMAN_CONTAINER_ID", container_id);
+    }
+
+    if let Some(exit_code) = session.exit_code {
+        cmd.env("DOORMAN_EXIT_CODE", exit_code.to_string());
+    }
+
+    debug!("Running {} hook {}", hook, program.display());
+
+    let status = cmd
+        .status()
+        .with_context(|| format!("Couldn't run {} hook {}", hook, program.display()))?;
+
+    if !status.success() {
+        return Err(anyhow!(
+            "{} hook {} failed with {}",
+            hook,
+            program.display(),
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+/// doorman's hooks, then the door's
+fn hooks<'a>(
+    config: &'a config::Config,
+    door: &'a Door,
+    pick: impl Fn(&'a Hooks) -> &'a Vec<PathBuf>,
+) -> impl Iterator<Item = &'a PathBuf> {
+    pick(&config.hooks)
+        .iter()
+        .chain(pick(&door.options.hooks).iter())
+}
+
+/// Runs the pre_launch hooks, stopping at the first one that fails
+pub fn pre_launch(config: &config::Config, session: &HookSession) -> Result<()> {
+    for program in hooks(config, session.door, |hooks| &hooks.pre_launch) {
+        run_hook(config, "pre_launch", program, session)
+            .with_context(|| format!("Sorry, {} can't be played right now", session.door.name))?;
+    }
+
+    Ok(())
+}
+
+/// Runs every post_exit hook, whether or not the others work
+pub fn post_exit(config: &config::Config, session: &HookSession) {
+    for program in hooks(config, session.door, |hooks| &hooks.post_exit) {
+        if let Err(err) = run_hook(config, "post_exit", program, session) {
+            warn!("{:#}", err);
+        }
+    }
+}
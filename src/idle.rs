@@ -0,0 +1,88 @@
+use super::config;
+use super::list;
+use super::stats;
+use super::who;
+use super::{IdleArgs, OutputFormat};
+use anyhow::{anyhow, Result};
+use chrono::serde::ts_seconds_option;
+use chrono::{DateTime, Duration, Utc};
+use chrono_humanize::{Accuracy, HumanTime, Tense};
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct IdleDoor {
+    pub door: String,
+
+    #[serde(with = "ts_seconds_option")]
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+/// Doors that nobody is using and nobody has used for at least threshold
+pub fn idle_doors(
+    door: &Option<String>,
+    threshold: Duration,
+    config: &config::Config,
+) -> Result<Vec<IdleDoor>> {
+    let names = match door {
+        Some(name) => vec![config.get_door(name)?.name],
+        None => config.door_names(),
+    };
+
+    let nodes = who::who(door, config)?;
+    let now = Utc::now();
+    let mut idle = vec![];
+
+    for name in names {
+        if nodes.iter().any(|node| node.door == name) {
+            continue;
+        }
+
+        let last_activity = stats::load(config, &config.get_door(&name)?)?.last_activity();
+
+        if last_activity.is_none_or(|last_activity| now - last_activity >= threshold) {
+            idle.push(IdleDoor {
+                door: name,
+                last_activity,
+            });
+        }
+    }
+
+    Ok(idle)
+}
+
+pub fn idle_command(args: &IdleArgs, config: &config::Config) -> Result<()> {
+    let doors = idle_doors(&args.door, Duration::minutes(args.minutes), config)?;
+
+    if let Some(format) = &args.format {
+        println!(
+            "{}",
+            match format {
+                OutputFormat::Json => serde_json::to_string(&doors)?,
+                OutputFormat::Yaml => serde_yaml::to_string(&doors)?,
+                OutputFormat::Csv => list::to_csv(&doors)?,
+            }
+        );
+    } else {
+        for door in doors.iter() {
+            match door.last_activity {
+                Some(last_activity) => println!(
+                    "{}: idle for {}",
+                    door.door,
+                    HumanTime::from(Utc::now().signed_duration_since(last_activity))
+                        .to_text_en(Accuracy::Rough, Tense::Present)
+                ),
+                None => println!("{}: never played", door.door),
+            }
+        }
+    }
+
+    // Exit with an error when nothing is idle, so scripts can do `doorman idle lord && ...`
+    if doors.is_empty() {
+        return Err(match &args.door {
+            Some(door) => anyhow!("{} hasn't been idle for {} minutes", door, args.minutes),
+            None => anyhow!("No doors have been idle for {} minutes", args.minutes),
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use super::config;
+use super::door;
+use super::list::{self, yes_no};
+use super::stats;
+use super::InfoArgs;
+use anyhow::Result;
+
+pub fn info_command(args: &InfoArgs, config: &config::Config) -> Result<()> {
+    let door = config.get_door(&args.door)?;
+
+    let mut busy = 0;
+    let mut nodes = vec![];
+
+    for node in door.options.node_numbers()? {
+        let status = match door::node_busy(config, &door.name, node) {
+            Ok(true) => {
+                busy += 1;
+                "busy".to_string()
+            }
+            Ok(false) if !config.may_use_node(&door, node) => "reserved".to_string(),
+            Ok(false) => "free".to_string(),
+            Err(err) => format!("unknown ({:#})", err),
+        };
+
+        nodes.push((node, status));
+    }
+
+    let dropfiles = door
+        .options
+        .dropfiles
+        .iter()
+        .map(|dropfile| dropfile.filename(1))
+        .collect::<Result<Vec<String>>>()?;
+
+    println!("Door:         {}", door.name);
+
+    if let Some(description) = &door.options.description {
+        println!("Description:  {}", description);
+    }
+
+    if let Some(category) = &door.options.category {
+        println!("Category:     {}", category);
+    }
+
+    if !door.options.tags.is_empty() {
+        println!("Tags:         {}", door.options.tags.join(", "));
+    }
+
+    println!(
+        "Door path:    {}{}",
+        door.options.door_path.display(),
+        if door.options.door_path.is_dir() {
+            ""
+        } else {
+            " (missing!)"
+        }
+    );
+    println!("Image:        {}", config.door_image(&door));
+
+    println!(
+        "Network:      {}",
+        door.options.network.as_deref().unwrap_or("none")
+    );
+
+    println!("Paging:       {}", yes_no(door.options.paging));
+
+    if let Some(minutes) = config.time_limit(&door, &config.user.username) {
+        println!("Time limit:   {} minutes", minutes);
+    }
+
+    if let Some(launches) = door.options.daily_launches {
+        println!("Launches:     {} a day", launches);
+    }
+
+    if let Some(term) = &door.options.term {
+        println!("TERM:         {}", term);
+    }
+
+    if let Some(terminfo) = &door.options.terminfo {
+        println!("Terminfo:     {}", terminfo.display());
+    }
+
+    println!(
+        "Maintenence:  {}",
+        yes_no(door::in_maintenence(config, &door.name)?)
+    );
+    println!("Nodes:        {} of {} busy", busy, nodes.len());
+
+    for (node, status) in nodes {
+        println!("  {:>3}:        {}", node, status);
+    }
+
+    println!(
+        "Configure:    {}",
+        yes_no(door.options.configure_commands.is_some())
+    );
+    let nightly_failures = stats::nightly_failures(config, &door);
+
+    println!(
+        "Nightly:      {}",
+        list::nightly_status(door.options.nightly_commands.is_some(), nightly_failures)
+    );
+
+    let nightly_log = config.rundir.join(format!("{}.nightly.log", door.name));
+
+    if nightly_failures > 0 && nightly_log.exists() {
+        println!("Nightly log:  {}", nightly_log.display());
+    }
+    println!(
+        "Warm-up:      {}",
+        yes_no(door.options.warmup_commands.is_some())
+    );
+    println!("Drop files:   {}", dropfiles.join(", "));
+
+    for requirement in door.options.requires.iter() {
+        println!(
+            "Requires:     {} ({})",
+            requirement.describe(),
+            match requirement.check() {
+                Ok(()) => "ok".to_string(),
+                Err(err) => format!("{:#}", err),
+            }
+        );
+    }
+    println!();
+    println!("DOORMAN.BAT for node 1:");
+    println!();
+
+    for line in door::render_launch_batch(config, &door, 1)?.lines() {
+        println!("    {}", line);
+    }
+
+    Ok(())
+}
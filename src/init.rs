@@ -0,0 +1,226 @@
+use super::config;
+use super::container;
+use super::dos::{Codepage, Templates};
+use super::editor;
+use super::user;
+use super::InitArgs;
+use anyhow::{anyhow, Context, Result};
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SAMPLE_DOOR: &str = "hello";
+
+const SKELETON: &str = include_str!("../templates/doorman.yml.hbr");
+
+#[derive(Serialize)]
+struct SkeletonVars<'a> {
+    bbs_name: String,
+    sysop_name: String,
+    username: String,
+    datadir: String,
+    rundir: String,
+    engine_path: String,
+    rootless_podman: bool,
+    sample_door: Option<&'a str>,
+    door_path: String,
+}
+
+#[derive(Serialize)]
+struct SampleVars {
+    bbs_name: String,
+    config_path: String,
+}
+
+/// Creates dir if needed and makes sure nobody but the sysop can write to it
+fn make_private_dir(dir: &Path, mode: u32) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Couldn't create {}", dir.display()))?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Couldn't set permissions on {}", dir.display()))?;
+
+    println!("Created {}", dir.display());
+
+    Ok(())
+}
+
+fn install_sample_door(
+    door_path: &Path,
+    template_dir: &Path,
+    bbs_name: &str,
+    config_path: &Path,
+) -> Result<()> {
+    fs::create_dir_all(door_path)
+        .with_context(|| format!("Couldn't create {}", door_path.display()))?;
+
+    let vars = SampleVars {
+        bbs_name: bbs_name.to_string(),
+        config_path: config_path.display().to_string(),
+    };
+
+    Templates::new(template_dir, Codepage::default()).write_dos("hello.bat", door_path, vars)?;
+
+    println!("Installed sample door in {}", door_path.display());
+
+    Ok(())
+}
+
+/// Quotes a string for YAML; JSON strings are valid YAML scalars
+fn quote(value: &str) -> Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+fn skeleton(
+    args: &InitArgs,
+    sysop: &user::User,
+    datadir: &Path,
+    rundir: &Path,
+    engine_path: &Path,
+    rootless_podman: bool,
+) -> Result<String> {
+    let door_path = datadir.join("doors").join(SAMPLE_DOOR);
+    let vars = SkeletonVars {
+        bbs_name: quote(&args.bbs_name)?,
+        sysop_name: quote(&sysop.display_name)?,
+        username: quote(&sysop.username)?,
+        datadir: quote(&datadir.display().to_string())?,
+        rundir: quote(&rundir.display().to_string())?,
+        engine_path: quote(&engine_path.display().to_string())?,
+        rootless_podman,
+        sample_door: (!args.no_sample_door).then_some(SAMPLE_DOOR),
+        door_path: quote(&door_path.display().to_string())?,
+    };
+
+    let mut hbars = Handlebars::new();
+    hbars.register_escape_fn(handlebars::no_escape);
+
+    Ok(hbars.render_template(SKELETON, &vars)?)
+}
+
+/// Makes sure the container engine can actually run containers, not just print its version
+fn check_engine(engine_path: &Path) -> Result<()> {
+    let output = Command::new(engine_path)
+        .arg("info")
+        .output()
+        .with_context(|| format!("Couldn't run {}", engine_path.display()))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} info failed: {}",
+            engine_path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn init_command(args: &InitArgs) -> Result<()> {
+    let config_path = config::config_path();
+
+    if config_path.exists() && !args.force {
+        return Err(anyhow!(
+            "{} already exists; use --force to replace it",
+            config_path.display()
+        ));
+    }
+
+    // Only whoever runs the existing setup gets to replace it
+    if config_path.exists() && !config::Config::load()?.is_sysop() {
+        return Err(anyhow!("Only sysops can replace the config!"));
+    }
+
+    if config::is_toml(&config_path) {
+        return Err(anyhow!(
+            "init writes YAML, so move {} out of the way first",
+            config_path.display()
+        ));
+    }
+
+    let sysop = user::User::calling_user()?;
+
+    let engine_path = container::find_engine()?;
+    let rootless_podman = container::is_rootless_podman(&engine_path)
+        .with_context(|| "Failed while checking for rootless podman")?;
+
+    println!(
+        "Found {} at {}",
+        container::engine_version(&engine_path)?,
+        engine_path.display()
+    );
+
+    if let Err(err) = check_engine(&engine_path) {
+        eprintln!("Warning: {:#}", err);
+        eprintln!("Doors won't launch until the container engine is working.");
+    }
+
+    let datadir = config::default_datadir();
+    let rundir = config::default_rundir(&datadir);
+
+    make_private_dir(&datadir, 0o750)?;
+    make_private_dir(&rundir, 0o700)?;
+
+    let contents = skeleton(
+        args,
+        &sysop,
+        &datadir,
+        &rundir,
+        &engine_path,
+        rootless_podman,
+    )?;
+    config::validate(&config_path, &contents)?;
+
+    if !args.no_sample_door {
+        install_sample_door(
+            &datadir.join("doors").join(SAMPLE_DOOR),
+            &datadir.join("templates"),
+            &args.bbs_name,
+            &config_path,
+        )?;
+    }
+
+    if config_path.exists() {
+        let backup_path = editor::write_config(&config_path, &contents)?;
+
+        println!(
+            "Replaced {}; the previous version is in {}",
+            config_path.display(),
+            backup_path.display()
+        );
+    } else {
+        let config_dir = config_path
+            .parent()
+            .map_or(PathBuf::from("."), PathBuf::from);
+
+        fs::create_dir_all(&config_dir)
+            .with_context(|| format!("Couldn't create {}", config_dir.display()))?;
+        fs::write(&config_path, &contents)
+            .with_context(|| format!("Couldn't write {}", config_path.display()))?;
+
+        println!("Wrote {}", config_path.display());
+    }
+
+    if args.pull {
+        let image = config::default_dosemu_image();
+
+        println!("Pulling {}...", image);
+
+        let status = Command::new(&engine_path)
+            .arg("pull")
+            .arg(&image)
+            .status()
+            .with_context(|| format!("While pulling {}", image))?;
+
+        if !status.success() {
+            return Err(anyhow!("Couldn't pull {}", image));
+        }
+    }
+
+    if !args.no_sample_door {
+        println!("All set! Try it out with: doorman launch {}", SAMPLE_DOOR);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use super::audit;
+use super::config::{self, Capability};
+use super::who::{self, WhoNode};
+use super::KickArgs;
+use anyhow::{anyhow, Result};
+
+fn matches(args: &KickArgs, session: &WhoNode) -> bool {
+    if let Some(container) = &args.container {
+        if !session.container_id.starts_with(container.as_str())
+            && !container.starts_with(session.container_id.as_str())
+        {
+            return false;
+        }
+    } else if session.node.is_none() {
+        // Sysops running maintenence have to be picked by container
+        return false;
+    }
+
+    if args.node.is_some() && session.node != args.node {
+        return false;
+    }
+
+    if let Some(user) = &args.user {
+        if &session.user != user {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn describe(session: &WhoNode) -> String {
+    match (session.node, &session.command) {
+        (Some(node), _) => format!("{} on {} node {}", session.user, session.door, node),
+        (None, Some(command)) => {
+            format!("{} running {} on {}", session.user, command, session.door)
+        }
+        (None, None) => format!("{} on {}", session.user, session.door),
+    }
+}
+
+fn stop(config: &config::Config, session: &WhoNode) -> Result<()> {
+    let mut stop = config.container_command("stop");
+    let status = stop.arg(&session.container_id).status()?;
+
+    if !status.success() {
+        return Err(anyhow!(
+            "'{} stop {}' exited with status {}",
+            stop.get_program().to_string_lossy(),
+            session.container_id,
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn kick_command(args: &KickArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Kick) {
+        return Err(anyhow!("Only sysops can kick people!"));
+    }
+
+    let door_name = match &args.door {
+        Some(door) => Some(config.get_door(door)?.name),
+        None => None,
+    };
+
+    let sessions: Vec<WhoNode> = who::who(&door_name, config)?
+        .into_iter()
+        .filter(|session| matches(args, session))
+        .collect();
+
+    if sessions.is_empty() {
+        return Err(anyhow!("Nobody matching that is playing right now."));
+    }
+
+    if sessions.len() > 1 && !args.all {
+        let found: Vec<String> = sessions.iter().map(describe).collect();
+
+        return Err(anyhow!(
+            "That matches {} sessions ({}); narrow it down or use --all",
+            sessions.len(),
+            found.join(", ")
+        ));
+    }
+
+    for session in sessions.iter() {
+        println!("Kicking {}", describe(session));
+        stop(config, session)?;
+
+        audit::record(
+            config,
+            "kick",
+            Some(&session.door),
+            session.node,
+            Some(format!("{} ({})", session.user, session.container_id)),
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use super::config;
+use super::stats;
+use super::{ListArgs, OutputFormat};
+use anyhow::Result;
+use comfy_table::modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS};
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::Table;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct DoorListing {
+    pub name: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    pub max_nodes: i8,
+    pub configure: bool,
+    pub nightly: bool,
+
+    /// Nightly runs that have failed in a row
+    pub nightly_failures: u32,
+}
+
+/// DoorListing flattened for CSV, which has nowhere to put a list of tags
+#[derive(Serialize, Debug)]
+struct DoorListingRow<'a> {
+    name: &'a str,
+    description: Option<&'a str>,
+    category: Option<&'a str>,
+    tags: String,
+    max_nodes: i8,
+    configure: bool,
+    nightly: bool,
+    nightly_failures: u32,
+}
+impl<'a> From<&'a DoorListing> for DoorListingRow<'a> {
+    fn from(door: &'a DoorListing) -> Self {
+        DoorListingRow {
+            name: &door.name,
+            description: door.description.as_deref(),
+            category: door.category.as_deref(),
+            tags: door.tags.join(" "),
+            max_nodes: door.max_nodes,
+            configure: door.configure,
+            nightly: door.nightly,
+            nightly_failures: door.nightly_failures,
+        }
+    }
+}
+
+pub fn list(category: &Option<String>, config: &config::Config) -> Result<Vec<DoorListing>> {
+    config
+        .door_names()
+        .into_iter()
+        .filter(|name| {
+            category
+                .as_ref()
+                .is_none_or(|category| config.in_category(name, category))
+        })
+        .map(|name| {
+            let door = config.get_door(&name)?;
+            let max_nodes = door.options.node_numbers()?.len() as i8;
+            let nightly_failures = stats::nightly_failures(config, &door);
+
+            Ok(DoorListing {
+                name: door.name,
+                description: door.options.description,
+                category: door.options.category,
+                tags: door.options.tags,
+                max_nodes,
+                configure: door.options.configure_commands.is_some(),
+                nightly: door.options.nightly_commands.is_some(),
+                nightly_failures,
+            })
+        })
+        .collect()
+}
+
+/// Whether a door has nightly maintenence, flagging it if the last runs failed
+pub fn nightly_status(nightly: bool, failures: u32) -> String {
+    match failures {
+        0 => yes_no(nightly).to_string(),
+        1 => "FAILED last run".to_string(),
+        failures => format!("FAILED last {} runs", failures),
+    }
+}
+
+pub fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Formats rows as CSV, with a header taken from the field names
+pub fn to_csv<T: Serialize>(rows: &[T]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+
+    for row in rows {
+        writer.serialize(row)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+pub fn print_list(format: &Option<OutputFormat>, doors: &Vec<DoorListing>) -> Result<()> {
+    if let Some(format) = format {
+        println!(
+            "{}",
+            match format {
+                OutputFormat::Json => serde_json::to_string(&doors)?,
+                OutputFormat::Yaml => serde_yaml::to_string(&doors)?,
+                OutputFormat::Csv => to_csv(
+                    &doors
+                        .iter()
+                        .map(DoorListingRow::from)
+                        .collect::<Vec<DoorListingRow>>()
+                )?,
+            }
+        );
+
+        return Ok(());
+    }
+
+    if doors.is_empty() {
+        println!("No doors found.");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
+        .set_header(vec![
+            "Door",
+            "Category",
+            "Description",
+            "Tags",
+            "Nodes",
+            "Configure",
+            "Nightly",
+        ]);
+
+    for door in doors {
+        table.add_row(vec![
+            door.name.clone(),
+            door.category.clone().unwrap_or("-".to_string()),
+            door.description.clone().unwrap_or("-".to_string()),
+            door.tags.join(", "),
+            door.max_nodes.to_string(),
+            yes_no(door.configure).to_string(),
+            nightly_status(door.nightly, door.nightly_failures),
+        ]);
+    }
+
+    println!("{table}");
+
+    Ok(())
+}
+
+pub fn list_command(args: &ListArgs, config: &config::Config) -> Result<()> {
+    let doors = list(&args.category, config)?;
+
+    print_list(&args.format, &doors)?;
+
+    Ok(())
+}
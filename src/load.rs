@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use serde::Deserialize;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Host limits above which new players are turned away, to protect sessions already running
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct LaunchLimits {
+    /// Highest 1-minute load average to allow launches at
+    pub max_load: Option<f64>,
+
+    /// Least available memory to allow launches with, in megabytes
+    pub min_free_memory: Option<u64>,
+
+    #[serde(default)]
+    /// Seconds to wait for the host to calm down before giving up. Defaults to 0.
+    pub wait: u64,
+}
+
+fn load_average() -> Result<f64> {
+    let loadavg =
+        fs::read_to_string("/proc/loadavg").with_context(|| "Couldn't read /proc/loadavg")?;
+
+    loadavg
+        .split_whitespace()
+        .next()
+        .and_then(|load| load.parse::<f64>().ok())
+        .ok_or(anyhow!("Couldn't parse /proc/loadavg"))
+}
+
+/// MemAvailable from /proc/meminfo, in megabytes
+fn free_memory() -> Result<u64> {
+    let meminfo =
+        fs::read_to_string("/proc/meminfo").with_context(|| "Couldn't read /proc/meminfo")?;
+
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))
+        .and_then(|value| {
+            value
+                .trim()
+                .trim_end_matches("kB")
+                .trim()
+                .parse::<u64>()
+                .ok()
+        })
+        .map(|kilobytes| kilobytes / 1024)
+        .ok_or(anyhow!("Couldn't find MemAvailable in /proc/meminfo"))
+}
+
+impl LaunchLimits {
+    /// Why the host is too busy for another player, if it is
+    fn too_busy(&self) -> Result<Option<String>> {
+        if let Some(max_load) = self.max_load {
+            let load = load_average()?;
+
+            debug!("Load average is {} (limit {})", load, max_load);
+
+            if load > max_load {
+                return Ok(Some(format!("load average is {:.2}", load)));
+            }
+        }
+
+        if let Some(min_free_memory) = self.min_free_memory {
+            let free = free_memory()?;
+
+            debug!("{} MB available (limit {} MB)", free, min_free_memory);
+
+            if free < min_free_memory {
+                return Ok(Some(format!("only {} MB of memory available", free)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Waits up to self.wait seconds for the host to have room for another player
+    pub fn check(&self) -> Result<()> {
+        let deadline = Instant::now() + Duration::from_secs(self.wait);
+        let mut told = false;
+
+        while let Some(reason) = self.too_busy()? {
+            if Instant::now() >= deadline {
+                debug!("Refusing launch: {}", reason);
+                return Err(anyhow!(
+                    "Sorry, the board is busy right now. Please try again shortly."
+                ));
+            }
+
+            if !told {
+                println!("The board is busy right now; waiting for room...");
+                told = true;
+            }
+
+            thread::sleep(Duration::from_secs(5).min(deadline - Instant::now()));
+        }
+
+        Ok(())
+    }
+}
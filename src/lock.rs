@@ -0,0 +1,152 @@
+use super::audit;
+use super::config::{self, Capability};
+use super::who;
+use super::{LockArgs, UnlockArgs};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// A sysop closing a door to new players, as kept in rundir/<door>.maintenance
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MaintenanceLock {
+    pub user: String,
+    pub since: DateTime<Utc>,
+    pub reason: Option<String>,
+
+    #[serde(default)]
+    /// When sessions still running on the door get ended
+    pub ends_sessions: Option<DateTime<Utc>>,
+}
+
+fn lock_path(config: &config::Config, door_name: &str) -> PathBuf {
+    config.rundir.join(format!("{}.maintenance", door_name))
+}
+
+/// The maintenance lock on a door, if a sysop has locked it
+pub fn maintenance_lock(
+    config: &config::Config,
+    door_name: &str,
+) -> Result<Option<MaintenanceLock>> {
+    let path = lock_path(config, door_name);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Couldn't read maintenance lock {}", path.display()))?;
+
+    let lock = serde_json::from_str(&contents)
+        .with_context(|| format!("Couldn't parse maintenance lock {}", path.display()))?;
+
+    Ok(Some(lock))
+}
+
+/// Turns the caller away from a locked door, unless they can lock doors themselves
+pub fn check(config: &config::Config, door: &config::Door) -> Result<()> {
+    let Some(lock) = maintenance_lock(config, &door.name)? else {
+        return Ok(());
+    };
+
+    if config.can(Capability::Lock) {
+        eprintln!(
+            "WARNING: {} is locked for maintenance by {}",
+            door.name, lock.user
+        );
+        return Ok(());
+    }
+
+    match lock.reason {
+        Some(reason) => Err(anyhow!(
+            "Sorry, {} is closed for maintenance: {}",
+            door.name,
+            reason
+        )),
+        None => Err(anyhow!("Sorry, {} is closed for maintenance.", door.name)),
+    }
+}
+
+pub fn lock_command(args: &LockArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Lock) {
+        return Err(anyhow!("Only sysops can lock doors!"));
+    }
+
+    let door = config.get_door(&args.door)?;
+    let path = lock_path(config, &door.name);
+
+    if let Some(lock) = maintenance_lock(config, &door.name)? {
+        return Err(anyhow!(
+            "{} has been locked by {} since {}",
+            door.name,
+            lock.user,
+            lock.since.format("%F %H:%M")
+        ));
+    }
+
+    let since = Utc::now();
+    let lock = MaintenanceLock {
+        user: config.user.username.clone(),
+        since,
+        reason: args.reason.clone(),
+        ends_sessions: args
+            .end_sessions
+            .map(|minutes| since + Duration::minutes(minutes.into())),
+    };
+
+    fs::write(&path, serde_json::to_string(&lock)?)
+        .with_context(|| format!("Couldn't write maintenance lock {}", path.display()))?;
+
+    audit::record(config, "lock", Some(&door.name), None, args.reason.clone());
+
+    println!(
+        "Locked {}; new players will be turned away until 'doorman unlock {}'.",
+        door.name, door.name
+    );
+
+    let playing = who::who(&Some(door.name.clone()), config)?
+        .iter()
+        .filter(|session| session.node.is_some())
+        .count();
+
+    if playing == 0 {
+        return Ok(());
+    }
+
+    match lock.ends_sessions {
+        Some(ends) => println!(
+            "Sessions still running: {}; they'll be ended at {}.",
+            playing,
+            ends.format("%F %H:%M")
+        ),
+        None => println!(
+            "Sessions still running: {}; 'doorman kick {}' will end them.",
+            playing, door.name
+        ),
+    }
+
+    Ok(())
+}
+
+pub fn unlock_command(args: &UnlockArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Lock) {
+        return Err(anyhow!("Only sysops can unlock doors!"));
+    }
+
+    let door = config.get_door(&args.door)?;
+    let path = lock_path(config, &door.name);
+
+    if !path.exists() {
+        return Err(anyhow!("{} isn't locked", door.name));
+    }
+
+    fs::remove_file(&path)
+        .with_context(|| format!("Couldn't remove maintenance lock {}", path.display()))?;
+
+    audit::record(config, "unlock", Some(&door.name), None, None);
+
+    println!("Unlocked {}.", door.name);
+
+    Ok(())
+}
@@ -1,45 +1,281 @@
 use anyhow::Result;
-use clap::{Args, Parser, ValueEnum};
+use clap::{value_parser, Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
+pub mod audit;
+pub mod bench;
+pub mod broadcast;
+pub mod bulletin;
+pub mod charset;
+pub mod checksum;
+pub mod cleanup;
 pub mod config;
 pub mod container;
 pub mod door;
 pub mod dos;
+pub mod editor;
+pub mod gc;
+pub mod guest;
+pub mod history;
+pub mod hooks;
+pub mod idle;
+pub mod info;
+pub mod init;
+pub mod kick;
+pub mod list;
+pub mod load;
+pub mod lock;
+pub mod menu;
+pub mod monitor;
+pub mod page;
+pub mod pull;
+pub mod queue;
+pub mod quota;
+pub mod random;
+pub mod report;
+pub mod requires;
+pub mod search;
+pub mod session;
+pub mod signature;
+pub mod simulate;
+pub mod stats;
+pub mod statsdb;
+pub mod storage;
+pub mod telemetry;
+pub mod timelimit;
+pub mod translate;
+pub mod update;
 pub mod user;
+pub mod validate;
+pub mod version;
+pub mod watch;
+pub mod webhook;
 pub mod who;
+pub mod wizard;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
+struct Cli {
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "DOORMAN_CONFIG",
+        value_name = "PATH"
+    )]
+    /// Config file to use instead of the one in ~/.config/doorman; through sudo or
+    /// doas, only sysops may use this
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
 enum Commands {
-    /// Launch a door
+    /// Launch a door, or pick one from a menu
     Launch(LaunchArgs),
 
+    /// Launch a door picked at random
+    Random(RandomArgs),
+
+    /// Get back into a game you're still playing, i.e. after your connection dropped
+    Attach(AttachArgs),
+
+    /// Look after a session until its container exits; launch starts this itself
+    #[command(hide = true)]
+    Monitor(MonitorArgs),
+
     /// Launch a door's configuration program
     Configure(SysopCmdArgs),
 
     /// Run a door's nighly maintenence
     Nightly(SysopCmdArgs),
 
+    /// Run a door's warm-up commands, i.e. at boot
+    Warmup(SysopCmdArgs),
+
+    /// List the configured doors
+    List(ListArgs),
+
+    /// Find doors by name, description, category, or tags
+    Search(SearchArgs),
+
+    /// Show how a door is set up and which nodes are busy
+    Info(InfoArgs),
+
     /// Show who's playing what
     Who(WhoArgs),
+
+    /// Show recent sessions: who played what, when, and for how long
+    History(HistoryArgs),
+
+    /// Show usage totals per door, per user, and per hour of the day
+    Stats(StatsArgs),
+
+    /// Write the top doors bulletin for the BBS to show
+    Bulletin,
+
+    /// Query the stats database
+    #[command(subcommand)]
+    StatsDb(StatsDbCommands),
+
+    /// Show doors nobody has played for a while, for scheduling quiet-time jobs
+    Idle(IdleArgs),
+
+    /// Spectate someone else's game
+    Watch(WatchArgs),
+
+    /// (SYSOP ONLY) Follow what's on a node's screen, read-only
+    Snoop(SnoopArgs),
+
+    /// Send a message to whoever is on a node of a door with paging turned on
+    Page(PageArgs),
+
+    /// End someone's session, i.e. if they're stuck or misbehaving
+    Kick(KickArgs),
+
+    /// Show a message on everyone's screen, i.e. before maintenence
+    Broadcast(BroadcastArgs),
+
+    /// Close a door to new players for maintenance
+    Lock(LockArgs),
+
+    /// Open a locked door to players again
+    Unlock(UnlockArgs),
+
+    /// Remove exited door containers, old door images, and unused doorman volumes
+    Gc(GcArgs),
+
+    /// Remove what crashed sessions left behind: exited containers, node rundirs, and lockfiles
+    Cleanup(CleanupArgs),
+
+    /// Check the registry for newer door images
+    CheckUpdates(CheckUpdatesArgs),
+
+    /// Pull the images doors run in ahead of time
+    Pull(PullArgs),
+
+    /// Check whether rundir and datadir are fast enough for doors
+    BenchIo(BenchIoArgs),
+
+    /// Load-test a door's node allocation and locking with simulated sessions
+    Simulate(SimulateArgs),
+
+    /// Set up doorman for the first time
+    Init(InitArgs),
+
+    /// Add a door to the configuration by answering some questions
+    AddDoor,
+
+    /// Check the configuration for typos and broken doors
+    Validate,
+
+    /// Check doors' critical files against their recorded checksums
+    Verify(VerifyArgs),
+
+    /// Manage doorman's configuration
+    #[command(subcommand)]
+    Config(ConfigCommands),
+
+    /// Show version and build information
+    Version(VersionArgs),
 }
 impl Commands {
     fn run(self) -> Result<()> {
-        let config = config::Config::load()?;
+        use config::Config;
 
         match self {
-            Commands::Launch(args) => door::launch(&args, config),
-            Commands::Configure(args) => door::configure(&args, &config),
-            Commands::Nightly(args) => door::nightly(&args, &config),
-            Commands::Who(args) => who::who_command(&args, &config),
+            Commands::Launch(args) => door::launch(&args, Config::load()?),
+            Commands::Random(args) => random::random_command(&args, Config::load()?),
+            Commands::Attach(args) => door::attach(&args, &Config::load()?),
+            Commands::Monitor(args) => monitor::monitor_command(&args, Config::load()?),
+            Commands::Configure(args) => door::configure(&args, &Config::load()?),
+            Commands::Nightly(args) => door::nightly(&args, &Config::load()?),
+            Commands::Warmup(args) => door::warmup(&args, &Config::load()?),
+            Commands::List(args) => list::list_command(&args, &Config::load()?),
+            Commands::Search(args) => search::search_command(&args, &Config::load()?),
+            Commands::Info(args) => info::info_command(&args, &Config::load()?),
+            Commands::Who(args) => who::who_command(&args, &Config::load()?),
+            Commands::History(args) => history::history_command(&args, &Config::load()?),
+            Commands::Stats(args) => report::stats_command(&args, &Config::load()?),
+            Commands::Bulletin => bulletin::bulletin_command(&Config::load()?),
+            Commands::StatsDb(command) => command.run(),
+            Commands::Idle(args) => idle::idle_command(&args, &Config::load()?),
+            Commands::Watch(args) => watch::watch_command(&args, &Config::load()?),
+            Commands::Snoop(args) => watch::snoop_command(&args, &Config::load()?),
+            Commands::Page(args) => page::page_command(&args, &Config::load()?),
+            Commands::Kick(args) => kick::kick_command(&args, &Config::load()?),
+            Commands::Broadcast(args) => broadcast::broadcast_command(&args, &Config::load()?),
+            Commands::Lock(args) => lock::lock_command(&args, &Config::load()?),
+            Commands::Unlock(args) => lock::unlock_command(&args, &Config::load()?),
+            Commands::Gc(args) => gc::gc_command(&args, &Config::load()?),
+            Commands::Cleanup(args) => cleanup::cleanup_command(&args, &Config::load()?),
+            Commands::CheckUpdates(args) => update::check_updates_command(&args, &Config::load()?),
+            Commands::Pull(args) => pull::pull_command(&args, &Config::load()?),
+            Commands::BenchIo(args) => bench::bench_io_command(&args, &Config::load()?),
+            Commands::Simulate(args) => simulate::simulate_command(&args, &Config::load()?),
+            Commands::Init(args) => init::init_command(&args),
+            Commands::AddDoor => wizard::add_door_command(&Config::load()?),
+            Commands::Validate => validate::validate_command(),
+            Commands::Verify(args) => checksum::verify_command(&args, &Config::load()?),
+            Commands::Config(command) => command.run(),
+            Commands::Version(args) => version::version_command(&args),
         }
     }
 }
 
-#[derive(Args, Debug)]
+#[derive(Subcommand, Debug)]
+enum ConfigCommands {
+    /// Edit the configuration (or just one door) in $EDITOR
+    Edit(ConfigEditArgs),
+
+    /// List saved versions of the configuration
+    History,
+
+    /// Restore a saved version of the configuration
+    Rollback(ConfigRollbackArgs),
+}
+impl ConfigCommands {
+    fn run(self) -> Result<()> {
+        use config::Config;
+
+        match self {
+            ConfigCommands::Edit(args) => editor::edit_command(&args, &Config::load()?),
+            ConfigCommands::History => editor::history_command(&Config::load()?),
+            ConfigCommands::Rollback(args) => editor::rollback_command(&args, &Config::load()?),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum StatsDbCommands {
+    /// Sessions and minutes played per door, by month
+    Monthly(StatsDbQueryArgs),
+
+    /// Sessions and minutes played by hour of the day
+    Hours(StatsDbQueryArgs),
+
+    /// Copy sessions from the history into the database, i.e. after turning it on
+    Import,
+}
+impl StatsDbCommands {
+    fn run(self) -> Result<()> {
+        use config::Config;
+
+        match self {
+            StatsDbCommands::Monthly(args) => statsdb::monthly_command(&args, &Config::load()?),
+            StatsDbCommands::Hours(args) => statsdb::hours_command(&args, &Config::load()?),
+            StatsDbCommands::Import => statsdb::import_command(&Config::load()?),
+        }
+    }
+}
+
+#[derive(Args, Debug, Default)]
 pub struct LaunchArgs {
-    door: String,
+    /// Door to launch; omit to choose from a menu
+    door: Option<String>,
 
     #[arg(short, long, value_name = "USERNAME")]
     /// (SYSOP ONLY) User to run the door as
@@ -56,6 +292,57 @@ pub struct LaunchArgs {
     #[arg(short, long)]
     /// Don't translate from ANSI+CP437
     raw: bool,
+
+    #[arg(long, conflicts_with = "raw")]
+    /// Translate from ANSI+CP437 even if the door is set to raw
+    no_raw: bool,
+
+    #[arg(long, value_name = "SOURCE")]
+    /// How the player got here, i.e. bbs or ssh-menu; defaults to $DOORMAN_SOURCE,
+    /// or ssh or local depending on the connection
+    source: Option<String>,
+
+    #[arg(long)]
+    /// Character set of your terminal; defaults to $DOORMAN_CHARSET, or cp437 with --raw
+    charset: Option<Charset>,
+
+    #[arg(short, long)]
+    /// Wait in line for a node if they're all busy
+    wait: bool,
+
+    #[arg(long, conflicts_with = "wait")]
+    /// Don't wait for a node even if the door is set to wait_for_node
+    no_wait: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RandomArgs {
+    #[arg(short = 'C', long)]
+    /// Only pick from doors in this category
+    category: Option<String>,
+
+    #[arg(short, long)]
+    /// Don't translate from ANSI+CP437
+    raw: bool,
+
+    #[arg(long, conflicts_with = "raw")]
+    /// Translate from ANSI+CP437 even if the door is set to raw
+    no_raw: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct AttachArgs {
+    /// (optional) Door to get back into; only needed if you're playing more than one
+    door: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct MonitorArgs {
+    /// Door the session is playing
+    door: String,
+
+    /// Node the session is on
+    node: i8,
 }
 
 #[derive(Args, Debug)]
@@ -69,9 +356,44 @@ pub struct SysopCmdArgs {
 
 #[derive(ValueEnum, Clone, Debug)]
 #[value(rename_all = "lower")]
-enum OutputFormat {
-    JSON,
-    YAML,
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[value(rename_all = "lower")]
+pub enum Charset {
+    Utf8,
+    Cp437,
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    #[arg(short = 'C', long)]
+    /// Only list doors in this category
+    category: Option<String>,
+
+    #[arg(short, long)]
+    /// Output format
+    format: Option<OutputFormat>,
+}
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    #[arg(required = true)]
+    /// What to look for, i.e. "trading game"
+    query: Vec<String>,
+
+    #[arg(short, long)]
+    /// Output format
+    format: Option<OutputFormat>,
+}
+
+#[derive(Args, Debug)]
+pub struct InfoArgs {
+    door: String,
 }
 
 #[derive(Args, Debug)]
@@ -79,12 +401,306 @@ pub struct WhoArgs {
     /// (optional) Only show people playing DOOR
     door: Option<String>,
 
+    #[arg(short = 'C', long)]
+    /// Only show people playing doors in this category
+    category: Option<String>,
+
+    #[arg(short, long, value_name = "USERNAME")]
+    /// Only show what this user is doing
+    user: Option<String>,
+
+    #[arg(short, long)]
+    /// Output format
+    format: Option<OutputFormat>,
+
+    #[arg(short, long)]
+    /// Show container IDs and impersonated sessions
+    long: bool,
+
+    #[arg(long)]
+    /// Character set of your terminal; defaults to $DOORMAN_CHARSET, or utf8
+    charset: Option<Charset>,
+
+    #[arg(
+        short,
+        long,
+        value_name = "SECONDS",
+        num_args = 0..=1,
+        default_missing_value = "5",
+        conflicts_with = "format"
+    )]
+    /// Keep redrawing the table, every 5 seconds unless told otherwise
+    watch: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// (optional) Only show sessions of DOOR
+    door: Option<String>,
+
+    #[arg(short = 'n', long, default_value_t = 20)]
+    /// How many sessions to show, most recent first
+    count: usize,
+
+    #[arg(short, long, value_name = "USERNAME")]
+    /// Only show this user's sessions; needs the stats capability for anyone else
+    user: Option<String>,
+
+    #[arg(short, long)]
+    /// Output format
+    format: Option<OutputFormat>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[arg(short, long)]
+    /// Only count sessions of this door
+    door: Option<String>,
+
+    #[arg(short, long, value_name = "USERNAME")]
+    /// Only count this user's sessions; needs the stats capability for anyone else
+    user: Option<String>,
+
+    #[arg(long, value_name = "DAYS")]
+    /// Only count sessions from the last DAYS days
+    days: Option<u32>,
+
+    #[arg(short, long)]
+    /// Output format
+    format: Option<OutputFormat>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsDbQueryArgs {
+    #[arg(short, long)]
+    /// Only count sessions of this door
+    door: Option<String>,
+
+    #[arg(short, long)]
+    /// Output format
+    format: Option<OutputFormat>,
+}
+
+#[derive(Args, Debug)]
+pub struct IdleArgs {
+    /// (optional) Only check DOOR; exits with an error if it isn't idle
+    door: Option<String>,
+
+    #[arg(short, long, default_value_t = 30)]
+    /// How long a door has to go unplayed to count as idle
+    minutes: i64,
+
     #[arg(short, long)]
     /// Output format
     format: Option<OutputFormat>,
 }
 
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    door: String,
+
+    #[arg(short, long)]
+    /// Node to watch; only needed if more than one person is playing
+    node: Option<i8>,
+}
+
+#[derive(Args, Debug)]
+pub struct SnoopArgs {
+    door: String,
+
+    /// Node to snoop on
+    node: i8,
+}
+
+#[derive(Args, Debug)]
+pub struct PageArgs {
+    door: String,
+
+    /// Node to page
+    node: i8,
+
+    #[arg(required = true)]
+    /// What to say
+    message: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct KickArgs {
+    #[arg(required_unless_present_any = ["user", "container"])]
+    /// Door to kick someone off of
+    door: Option<String>,
+
+    #[arg(short, long, requires = "door")]
+    /// Only kick whoever is on this node
+    node: Option<i8>,
+
+    #[arg(short, long, value_name = "USERNAME")]
+    /// Only kick this user
+    user: Option<String>,
+
+    #[arg(long, value_name = "ID")]
+    /// Only kick the session in this container; also works for maintenence
+    container: Option<String>,
+
+    #[arg(short, long)]
+    /// Kick everyone who matches instead of refusing when there's more than one
+    all: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct BroadcastArgs {
+    #[arg(short, long)]
+    /// Only show the message to people playing this door
+    door: Option<String>,
+
+    #[arg(required = true)]
+    /// What to say, i.e. "Nightly maintenence in 5 minutes"
+    message: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct LockArgs {
+    /// Door to lock
+    door: String,
+
+    #[arg(short, long)]
+    /// Why, to tell whoever gets turned away
+    reason: Option<String>,
+
+    #[arg(long, value_name = "MINUTES")]
+    /// End running sessions this many minutes from now, warning their players first
+    end_sessions: Option<u32>,
+}
+
+#[derive(Args, Debug)]
+pub struct UnlockArgs {
+    /// Door to unlock
+    door: String,
+}
+
+#[derive(Args, Debug)]
+pub struct GcArgs {
+    #[arg(short = 'n', long)]
+    /// Only show what would be removed
+    dry_run: bool,
+
+    #[arg(short, long, value_name = "HOURS", default_value_t = 24)]
+    /// Only remove containers that were created at least this long ago
+    older_than: i64,
+}
+
+#[derive(Args, Debug)]
+pub struct CleanupArgs {
+    #[arg(short = 'n', long)]
+    /// Only show what would be removed
+    dry_run: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct CheckUpdatesArgs {
+    #[arg(short, long)]
+    /// (SYSOP ONLY) Pull new images if there are any
+    pull: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct PullArgs {
+    /// (optional) Only pull the image DOOR runs in
+    door: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchIoArgs {
+    #[arg(short, long, default_value_t = 50, value_parser = value_parser!(u32).range(1..))]
+    /// Number of drop files to write and locks to take
+    iterations: u32,
+
+    #[arg(short, long, value_name = "MB", default_value_t = 16)]
+    /// Size of the throughput test file
+    megabytes: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct SimulateArgs {
+    #[arg(short, long)]
+    /// Door to simulate sessions of
+    door: String,
+
+    #[arg(short, long, default_value_t = 4)]
+    /// Number of sessions to start at once
+    sessions: u32,
+
+    #[arg(short = 't', long, value_name = "SECONDS", default_value_t = 10)]
+    /// How long each session holds its node
+    duration: u64,
+}
+
+#[derive(Args, Debug)]
+pub struct InitArgs {
+    #[arg(short, long, default_value = "My BBS")]
+    /// Name of the BBS, for drop files
+    bbs_name: String,
+
+    #[arg(short, long)]
+    /// Pull the dosemu image now instead of on first launch
+    pull: bool,
+
+    #[arg(long)]
+    /// Don't install the sample door
+    no_sample_door: bool,
+
+    /// (SYSOP ONLY) Replace an existing config
+    /// Replace an existing config
+    force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    /// (optional) Only check DOOR
+    door: Option<String>,
+
+    #[arg(short, long)]
+    /// Record the current checksums instead of checking them
+    update: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigEditArgs {
+    /// Only edit this door's settings
+    door: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigRollbackArgs {
+    #[arg(default_value_t = 1)]
+    /// Version to restore, as numbered by 'config history'; defaults to the newest
+    number: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct VersionArgs {
+    #[arg(short, long)]
+    /// Output as JSON
+    json: bool,
+}
+
 fn main() -> Result<()> {
     env_logger::init();
-    Commands::parse().run()
+
+    let cli = Cli::parse();
+
+    if let Some(path) = cli.config {
+        if config::may_choose_config() {
+            config::set_config_path(path);
+        } else {
+            eprintln!(
+                "WARNING: ignoring config file {}; only sysops can choose one",
+                path.display()
+            );
+        }
+    }
+
+    cli.command
+        .unwrap_or_else(|| Commands::Launch(LaunchArgs::default()))
+        .run()
 }
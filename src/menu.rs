@@ -0,0 +1,70 @@
+use super::charset;
+use super::config;
+use super::Charset;
+use anyhow::{anyhow, Result};
+use std::io::{self, IsTerminal, Write};
+
+pub fn prompt(question: &str) -> Result<String> {
+    print!("{} ", question);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(answer.trim().to_string())
+}
+
+pub fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(true);
+    }
+
+    let answer = prompt(&format!("{} [Y/n]", question))?;
+
+    Ok(!answer.to_lowercase().starts_with('n'))
+}
+
+pub fn choose_door(config: &config::Config, charset: Charset) -> Result<Option<String>> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "No door given, and can't show a menu without a terminal"
+        ));
+    }
+
+    if config.door_names().is_empty() {
+        return Err(anyhow!("No doors are configured!"));
+    }
+
+    let names = config.playable_door_names();
+
+    if names.is_empty() {
+        return Err(anyhow!("None of the doors are open to you!"));
+    }
+
+    println!("Available doors:");
+    println!();
+
+    for (index, name) in names.iter().enumerate() {
+        charset::print_line(charset, &format!("  {:>2}) {}", index + 1, name))?;
+    }
+
+    println!();
+
+    loop {
+        let answer = prompt("Which door would you like to play? (Q to quit)")?;
+
+        if answer.is_empty() || answer.eq_ignore_ascii_case("q") {
+            return Ok(None);
+        }
+
+        if let Ok(choice) = answer.parse::<usize>() {
+            if choice >= 1 && choice <= names.len() {
+                return Ok(Some(names[choice - 1].clone()));
+            }
+        } else if let Some(name) = names.iter().find(|name| name.eq_ignore_ascii_case(&answer)) {
+            return Ok(Some(name.clone()));
+        }
+
+        println!("Sorry, I don't know that one.");
+    }
+}
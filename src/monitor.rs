@@ -0,0 +1,321 @@
+use super::audit;
+use super::broadcast;
+use super::config;
+use super::door;
+use super::history::{self, HistoryEntry};
+use super::hooks::{self, HookSession};
+use super::lock;
+use super::session::{self, SessionState};
+use super::stats;
+use super::statsdb;
+use super::timelimit;
+use super::webhook::{self, WebhookEvent};
+use super::MonitorArgs;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use fs4::FileExt;
+use log::{debug, warn};
+use std::env;
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// How often to check on the clock while the container runs
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Starts watching the session on node of door in the background, so that it gets
+/// wrapped up when its container exits, whether or not the player is still attached
+pub fn spawn(door: &config::Door, node: i8) -> Result<()> {
+    let doorman = env::current_exe().context("Couldn't find the doorman executable")?;
+
+    Command::new(doorman)
+        .arg("--config")
+        .arg(config::config_path())
+        .arg("monitor")
+        .arg(&door.name)
+        .arg(node.to_string())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // Hanging up on the player shouldn't take the monitor with it
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("Couldn't start monitor for {} node {}", door.name, node))?;
+
+    Ok(())
+}
+
+/// Why a session's container is being stopped before it exits by itself
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Ending {
+    TimeUp,
+    Maintenance,
+}
+impl Ending {
+    fn warning(self, door: &config::Door, minutes: i64) -> String {
+        let plural = if minutes == 1 { "" } else { "s" };
+
+        match self {
+            Ending::TimeUp => format!("{} minute{} left in this session", minutes, plural),
+            Ending::Maintenance => format!(
+                "{} is closing for maintenance in {} minute{}",
+                door.name, minutes, plural
+            ),
+        }
+    }
+
+    fn notice(self, door: &config::Door) -> String {
+        match self {
+            Ending::TimeUp => "Sorry, your time is up!".to_string(),
+            Ending::Maintenance => format!("Sorry, {} is closing for maintenance.", door.name),
+        }
+    }
+
+    fn event(self) -> &'static str {
+        match self {
+            Ending::TimeUp => "time_up",
+            Ending::Maintenance => "maintenance_end",
+        }
+    }
+}
+
+/// Whichever of the session's time limit and a maintenance lock on its door is going
+/// to end it first, if either is
+fn next_ending(
+    config: &config::Config,
+    door: &config::Door,
+    time_up: Option<DateTime<Utc>>,
+) -> Option<(Ending, DateTime<Utc>)> {
+    let maintenance = match lock::maintenance_lock(config, &door.name) {
+        Ok(lock) => lock.and_then(|lock| lock.ends_sessions),
+        Err(err) => {
+            debug!("{:#}", err);
+            None
+        }
+    };
+
+    [
+        (Ending::TimeUp, time_up),
+        (Ending::Maintenance, maintenance),
+    ]
+    .into_iter()
+    .filter_map(|(ending, at)| Some((ending, at?)))
+    .min_by_key(|(_, at)| *at)
+}
+
+/// Writes message to the player's terminal, if they're attached
+fn tell_player(config: &config::Config, state: &SessionState, message: &str) {
+    let message = format!("\r\n\x07*** {} ***\r\n", message);
+
+    if let Err(err) = broadcast::send(config, &state.container_id, &message) {
+        debug!("{:#}", err);
+    }
+}
+
+/// Waits for the session's container to exit, warning the player as its time limit or
+/// a maintenance lock gets close and stopping it when one arrives, and returns its
+/// exit code
+fn wait(
+    config: &config::Config,
+    door: &config::Door,
+    state: &SessionState,
+    time_up: Option<DateTime<Utc>>,
+) -> Option<i32> {
+    let container_id = &state.container_id;
+    let waiter = config
+        .container_command("wait")
+        .arg(container_id)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let mut waiter = match waiter {
+        Ok(waiter) => waiter,
+        Err(err) => {
+            warn!("Couldn't wait for {}: {}", container_id, err);
+            return None;
+        }
+    };
+
+    let mut warned = Vec::new();
+    let mut stopped = false;
+
+    while let Ok(None) = waiter.try_wait() {
+        if let Some((ending, at)) = next_ending(config, door, time_up).filter(|_| !stopped) {
+            let left = at - Utc::now();
+
+            // Starting with 3 minutes left warns about 3, not 5
+            let mark = timelimit::WARNINGS
+                .into_iter()
+                .filter(|&minutes| left <= chrono::Duration::minutes(minutes))
+                .min();
+
+            if left <= chrono::Duration::zero() {
+                stop(config, door, state, ending);
+                stopped = true;
+            } else if let Some(mark) = mark.filter(|&mark| !warned.contains(&(ending, mark))) {
+                let minutes = (left.num_seconds() + 59) / 60;
+
+                tell_player(config, state, &ending.warning(door, minutes));
+                warned.push((ending, mark));
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    match waiter.wait_with_output() {
+        Ok(output) if output.status.success() => {
+            String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+        }
+        Ok(output) => {
+            warn!("Couldn't wait for {}: {}", container_id, output.status);
+            None
+        }
+        Err(err) => {
+            warn!("Couldn't wait for {}: {}", container_id, err);
+            None
+        }
+    }
+}
+
+/// Stops a session's container before it exits by itself
+fn stop(config: &config::Config, door: &config::Door, state: &SessionState, ending: Ending) {
+    debug!("Stopping {}: {:?}", state.container_id, ending);
+
+    audit::record(
+        config,
+        ending.event(),
+        Some(&door.name),
+        Some(state.node),
+        None,
+    );
+    tell_player(config, state, &ending.notice(door));
+
+    let status = config
+        .container_command("stop")
+        .arg(&state.container_id)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+
+    match status {
+        Ok(status) if status.success() => (),
+        Ok(status) => warn!("Couldn't stop {}: {}", state.container_id, status),
+        Err(err) => warn!("Couldn't stop {}: {}", state.container_id, err),
+    }
+}
+
+/// Records a session that's over
+fn session_ended(
+    config: &config::Config,
+    door: &config::Door,
+    state: &SessionState,
+    exit_code: Option<i32>,
+) {
+    let entry = HistoryEntry {
+        user: state.user.clone(),
+        display_name: state.display_name.clone(),
+        door: door.name.clone(),
+        node: state.node,
+        start: state.since,
+        end: Utc::now(),
+        exit_code,
+    };
+
+    if let Err(err) = history::record(config, &entry) {
+        warn!("Couldn't record session in history: {:#}", err);
+    }
+
+    if let Err(err) = statsdb::record(config, &entry) {
+        warn!("Couldn't record session in stats database: {:#}", err);
+    }
+
+    if let Err(err) = stats::record_session_end(config, door) {
+        warn!("Couldn't record end of session: {:#}", err);
+    }
+
+    webhook::notify(
+        config,
+        WebhookEvent::Exit,
+        &door.name,
+        Some(state.node),
+        None,
+    );
+
+    hooks::post_exit(
+        config,
+        &HookSession {
+            door,
+            node: state.node,
+            rundir: &state.rundir,
+            container_id: Some(&state.container_id),
+            exit_code,
+        },
+    );
+
+    if let Err(err) = session::remove(config, &door.name, state.node, &state.container_id) {
+        warn!("{:#}", err);
+    }
+}
+
+/// Held by whichever monitor is looking after the session in container_id
+fn lock_path(config: &config::Config, state: &SessionState) -> PathBuf {
+    config.rundir.join(format!(
+        "{}.{}.{}.monitor.lock",
+        state.door, state.node, state.container_id
+    ))
+}
+
+/// Whether the caller started the session, or can look after anyone's
+fn may_monitor(config: &config::Config, state: &SessionState) -> bool {
+    config.is_sysop()
+        || state.user == config.user.username
+        || state.impersonated_by.as_ref() == Some(&config.user.username)
+        || state.uid == Some(config.user.uid)
+}
+
+pub fn monitor_command(args: &MonitorArgs, mut config: config::Config) -> Result<()> {
+    let door = config.get_door(&args.door)?;
+    let mut state = SessionState::read(&config, &door.name, args.node)?;
+
+    if !may_monitor(&config, &state) {
+        return Err(anyhow!("That isn't your session!"));
+    }
+
+    // A second monitor would record the session twice
+    let lock_path = lock_path(&config, &state);
+    let lockfile = door::make_lockfile(&lock_path)?;
+
+    if lockfile.try_lock_exclusive().is_err() {
+        debug!("{} already has a monitor", state.container_id);
+        return Ok(());
+    }
+
+    // From here on, the session's state file says we're the one looking after it
+    state.pid = process::id();
+    state.write(&config)?;
+
+    // Guests and impersonated players aren't who doorman was started as
+    config.user.uid = state.uid.unwrap_or(config.user.uid);
+    config.user.username = state.user.clone();
+    config.user.display_name = state.display_name.clone().unwrap_or(state.user.clone());
+    config.impersonated_by = state.impersonated_by.clone();
+
+    debug!("Watching {} for {}", state.container_id, state.user);
+
+    let time_up = timelimit::deadline(state.since, state.time_limit);
+    let exit_code = wait(&config, &door, &state, time_up);
+
+    session_ended(&config, &door, &state, exit_code);
+
+    // Nobody else locks this container's file, so it can go while we hold it
+    if let Err(err) = fs::remove_file(&lock_path) {
+        debug!("Couldn't remove {}: {}", lock_path.display(), err);
+    }
+
+    Ok(())
+}
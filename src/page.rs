@@ -0,0 +1,105 @@
+use super::config::{self, Capability};
+use super::door;
+use super::who;
+use super::PageArgs;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// DOS drive each node of a door with paging sees the shared page directory as
+pub const PAGE_DRIVE: &str = "p:";
+
+/// Directory in rundir shared by every node of a door, holding one inbox per node
+pub fn pages_dir(config: &config::Config, door_name: &str) -> PathBuf {
+    config.rundir.join(format!("{}.pages", door_name))
+}
+
+/// Name of a node's inbox in the page directory, i.e. NODE1.TXT
+pub fn inbox_name(node: i8) -> String {
+    format!("NODE{}.TXT", node)
+}
+
+/// Creates the door's page directory and empties node's inbox, so a new session
+/// doesn't see pages meant for the last person on the node
+pub fn prepare(config: &config::Config, door: &config::Door, node: i8) -> Result<PathBuf> {
+    let dir = pages_dir(config, &door.name);
+
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Couldn't create page directory {}", dir.display()))?;
+
+    let inbox = dir.join(inbox_name(node));
+
+    if inbox.exists() {
+        fs::remove_file(&inbox)
+            .with_context(|| format!("Couldn't clear old pages in {}", inbox.display()))?;
+    }
+
+    Ok(dir)
+}
+
+/// Appends a page to node's inbox, in the door's code page
+pub fn send(
+    config: &config::Config,
+    door: &config::Door,
+    node: i8,
+    from: &str,
+    message: &str,
+) -> Result<()> {
+    let dir = pages_dir(config, &door.name);
+
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Couldn't create page directory {}", dir.display()))?;
+
+    let inbox = dir.join(inbox_name(node));
+    let line = format!("{}: {}\r\n", from, message);
+
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&inbox)
+        .and_then(|mut file| file.write_all(&door.options.codepage.encode(&line)))
+        .with_context(|| format!("Couldn't write page to {}", inbox.display()))
+}
+
+pub fn page_command(args: &PageArgs, config: &config::Config) -> Result<()> {
+    let door = config.get_door(&args.door)?;
+
+    config.check_may_play(&door)?;
+
+    if !door.options.paging {
+        return Err(anyhow!("Paging isn't turned on for {}.", door.name));
+    }
+
+    // Paging is for players of the door to reach each other; sysops can reach anyone
+    let playing = who::who(&Some(door.name.clone()), config)?
+        .iter()
+        .any(|session| session.node.is_some() && session.user == config.user.username);
+
+    if !playing && !config.can(Capability::Kick) {
+        return Err(anyhow!(
+            "You can only page people on {} while you're playing it.",
+            door.name
+        ));
+    }
+
+    if !door::node_busy(config, &door.name, args.node)? {
+        return Err(anyhow!(
+            "Nobody is playing {} on node {} right now.",
+            door.name,
+            args.node
+        ));
+    }
+
+    send(
+        config,
+        &door,
+        args.node,
+        &config.user.display_name,
+        &args.message.join(" "),
+    )?;
+
+    println!("Paged node {} of {}.", args.node, door.name);
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use super::config::{self, Capability};
+use super::PullArgs;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::process::Stdio;
+
+/// When doorman pulls door images
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PullPolicy {
+    /// Pull before every launch, to pick up updates right away
+    Always,
+
+    /// Pull images that haven't been pulled yet
+    #[default]
+    Missing,
+
+    /// Never pull; images have to be pulled ahead of time with 'doorman pull'
+    Never,
+}
+
+/// Whether image has already been pulled
+pub fn image_present(config: &config::Config, image: &str) -> Result<bool> {
+    let status = config
+        .container_command("image")
+        .arg("inspect")
+        .arg(image)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .with_context(|| format!("While checking for {}", image))?;
+
+    Ok(status.success())
+}
+
+/// Pulls image, showing the engine's progress
+pub fn pull_image(config: &config::Config, image: &str) -> Result<()> {
+    let status = config
+        .container_command("pull")
+        .arg(image)
+        .status()
+        .with_context(|| format!("While pulling {}", image))?;
+
+    if !status.success() {
+        return Err(anyhow!("Pulling {} failed with status {}", image, status));
+    }
+
+    Ok(())
+}
+
+/// Makes sure image is ready to run according to the pull policy, so a pull
+/// doesn't happen silently inside the engine's run with no feedback
+pub fn ensure_image(config: &config::Config, image: &str) -> Result<()> {
+    match config.pull_policy {
+        PullPolicy::Always => {
+            eprintln!("Checking for updates to {}...", image);
+            pull_image(config, image)
+        }
+        PullPolicy::Missing if !image_present(config, image)? => {
+            eprintln!(
+                "Pulling {}; this can take a few minutes the first time...",
+                image
+            );
+            pull_image(config, image)
+        }
+        PullPolicy::Never if !image_present(config, image)? => Err(anyhow!(
+            "{} hasn't been pulled; ask the sysop to run 'doorman pull'",
+            image
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub fn pull_command(args: &PullArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Nightly) {
+        return Err(anyhow!("Only sysops can pull images!"));
+    }
+
+    let images = match &args.door {
+        Some(name) => vec![config.door_image(&config.get_door(name)?).to_string()],
+        None => config.images(),
+    };
+
+    for image in images {
+        println!("Pulling {}...", image);
+        pull_image(config, &image)?;
+    }
+
+    Ok(())
+}
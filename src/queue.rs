@@ -0,0 +1,147 @@
+use super::config;
+use super::door;
+use super::session;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use fs4::FileExt;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// How often callers in line check for a free node
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A caller waiting for one of a door's nodes
+#[derive(Serialize, Deserialize, Debug)]
+struct Ticket {
+    pid: u32,
+    user: String,
+    since: DateTime<Utc>,
+}
+
+fn queue_path(config: &config::Config, door_name: &str) -> PathBuf {
+    config.rundir.join(format!("{}.queue", door_name))
+}
+
+/// Changes the line for door_name under an exclusive lock, after dropping callers
+/// who have given up
+fn update<T, F: FnOnce(&mut Vec<Ticket>) -> T>(
+    config: &config::Config,
+    door_name: &str,
+    change: F,
+) -> Result<T> {
+    let path = queue_path(config, door_name);
+    let mut file = fs::File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Couldn't open queue {}", path.display()))?;
+
+    file.lock_exclusive()?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    let mut tickets: Vec<Ticket> = if contents.trim().is_empty() {
+        vec![]
+    } else {
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Couldn't parse queue {}", path.display()))?
+    };
+
+    tickets.retain(|ticket| session::alive(ticket.pid));
+
+    let result = change(&mut tickets);
+
+    file.rewind()?;
+    file.set_len(0)?;
+    file.write_all(serde_json::to_string(&tickets)?.as_bytes())?;
+
+    Ok(result)
+}
+
+/// How many callers are waiting for door_name
+pub fn waiting(config: &config::Config, door_name: &str) -> Result<usize> {
+    update(config, door_name, |tickets| tickets.len())
+}
+
+/// The caller's place in line; leaves the line when dropped
+struct Place<'a> {
+    config: &'a config::Config,
+    door_name: &'a str,
+}
+impl Place<'_> {
+    /// 1 for the front of the line; gets in line at the back if we aren't already
+    fn position(&self) -> Result<usize> {
+        update(self.config, self.door_name, |tickets| {
+            let pid = std::process::id();
+
+            if let Some(index) = tickets.iter().position(|ticket| ticket.pid == pid) {
+                return index + 1;
+            }
+
+            tickets.push(Ticket {
+                pid,
+                user: self.config.user.username.clone(),
+                since: Utc::now(),
+            });
+
+            tickets.len()
+        })
+    }
+}
+impl Drop for Place<'_> {
+    fn drop(&mut self) {
+        let result = update(self.config, self.door_name, |tickets| {
+            tickets.retain(|ticket| ticket.pid != std::process::id())
+        });
+
+        if let Err(err) = result {
+            warn!("Couldn't leave the line for {}: {:#}", self.door_name, err);
+        }
+    }
+}
+
+/// Waits in line for a node of door, taking turns with everyone else waiting
+pub fn wait_for_node(
+    config: &config::Config,
+    door: &config::Door,
+) -> Result<(i8, PathBuf, fs::File)> {
+    let place = Place {
+        config,
+        door_name: &door.name,
+    };
+    let mut last_position = None;
+
+    loop {
+        let position = place.position()?;
+
+        debug!("Number {} in line for {}", position, door.name);
+
+        if let Some(node) = door::try_reserved_node_lockfile(door, config)? {
+            return Ok(node);
+        }
+
+        if position == 1 {
+            if let Some(node) = door::try_node_lockfile(door, config)? {
+                return Ok(node);
+            }
+        }
+
+        if last_position != Some(position) {
+            eprintln!(
+                "All nodes for {} are busy; you're number {} in line. Hang on, or press Ctrl-C to give up.",
+                door.name, position
+            );
+            last_position = Some(position);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
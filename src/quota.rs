@@ -0,0 +1,131 @@
+use super::config;
+use anyhow::{anyhow, Context, Result};
+use chrono::{Local, NaiveDate};
+use fs4::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+
+/// How many times each user has launched each door today
+#[derive(Serialize, Deserialize, Debug)]
+struct LaunchCounts {
+    date: NaiveDate,
+
+    #[serde(default)]
+    launches: HashMap<String, HashMap<String, u32>>,
+}
+impl LaunchCounts {
+    fn today() -> LaunchCounts {
+        LaunchCounts {
+            date: Local::now().date_naive(),
+            launches: HashMap::new(),
+        }
+    }
+}
+
+fn counts_path(config: &config::Config) -> PathBuf {
+    config.datadir.join("launches.json")
+}
+
+fn open_counts(config: &config::Config) -> Result<fs::File> {
+    let path = counts_path(config);
+
+    fs::File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Couldn't open launch counts {}", path.display()))
+}
+
+/// Today's counts; yesterday's don't matter anymore
+fn read_counts(file: &mut fs::File) -> Result<LaunchCounts> {
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    if contents.trim().is_empty() {
+        return Ok(LaunchCounts::today());
+    }
+
+    let counts: LaunchCounts = serde_json::from_str(&contents)?;
+
+    if counts.date != Local::now().date_naive() {
+        return Ok(LaunchCounts::today());
+    }
+
+    Ok(counts)
+}
+
+/// Errors if counts show the caller has used up today's launches, of door or overall
+fn check_counts(config: &config::Config, door: &config::Door, counts: &LaunchCounts) -> Result<()> {
+    let overall_limit = config.daily_launches;
+    let door_limit = door.options.daily_launches;
+    let launches = counts.launches.get(&config.user.username);
+    let of_door = launches
+        .and_then(|launches| launches.get(&door.name))
+        .copied()
+        .unwrap_or(0);
+    let overall: u32 = launches.map_or(0, |launches| launches.values().sum());
+
+    if let Some(limit) = door_limit.filter(|limit| of_door >= *limit) {
+        return Err(anyhow!(
+            "You've used up today's plays of {} ({} a day). Come back tomorrow!",
+            door.name,
+            limit
+        ));
+    }
+
+    if let Some(limit) = overall_limit.filter(|limit| overall >= *limit) {
+        return Err(anyhow!(
+            "You've used up today's door launches ({} a day). Come back tomorrow!",
+            limit
+        ));
+    }
+
+    Ok(())
+}
+
+/// Turns the caller away early if they've used up today's launches, of door or overall
+pub fn check(config: &config::Config, door: &config::Door) -> Result<()> {
+    if config.daily_launches.is_none() && door.options.daily_launches.is_none() {
+        return Ok(());
+    }
+
+    let mut file = open_counts(config)?;
+
+    file.lock_shared()?;
+
+    check_counts(config, door, &read_counts(&mut file)?)
+}
+
+/// Counts a launch of door against the caller's daily limits, unless they've used
+/// them up; checking and counting under one lock keeps simultaneous launches honest
+pub fn claim_launch(config: &config::Config, door: &config::Door) -> Result<()> {
+    if config.daily_launches.is_none() && door.options.daily_launches.is_none() {
+        return Ok(());
+    }
+
+    let mut file = open_counts(config)?;
+
+    file.lock_exclusive()?;
+
+    let mut counts = read_counts(&mut file)?;
+
+    check_counts(config, door, &counts)?;
+
+    *counts
+        .launches
+        .entry(config.user.username.clone())
+        .or_default()
+        .entry(door.name.clone())
+        .or_default() += 1;
+
+    file.rewind()?;
+    file.set_len(0)?;
+    file.write_all(serde_json::to_string(&counts)?.as_bytes())?;
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use super::config;
+use super::door;
+use super::requires;
+use super::{LaunchArgs, RandomArgs};
+use anyhow::{anyhow, Result};
+
+/// Whether a new player could get into the door right now
+fn available(config: &config::Config, door_name: &str) -> bool {
+    let Ok(door) = config.get_door(door_name) else {
+        return false;
+    };
+
+    if !config.may_play(&door)
+        || door::in_maintenence(config, door_name).unwrap_or(true)
+        || requires::check_requirements(&door).is_err()
+    {
+        return false;
+    }
+
+    let Ok(nodes) = door.options.node_numbers() else {
+        return false;
+    };
+
+    nodes.into_iter().any(|node| {
+        config.may_use_node(&door, node)
+            && !door::node_busy(config, door_name, node).unwrap_or(true)
+    })
+}
+
+pub fn random_command(args: &RandomArgs, config: config::Config) -> Result<()> {
+    let names: Vec<String> = config
+        .door_names()
+        .into_iter()
+        .filter(|name| {
+            args.category
+                .as_ref()
+                .is_none_or(|category| config.in_category(name, category))
+        })
+        .filter(|name| available(&config, name))
+        .collect();
+
+    if names.is_empty() {
+        return Err(match &args.category {
+            Some(category) => anyhow!("No doors in '{}' are free right now!", category),
+            None => anyhow!("No doors are free right now!"),
+        });
+    }
+
+    let name = names[fastrand::usize(..names.len())].clone();
+
+    println!("Surprise! You're playing {}.", name);
+
+    door::launch(
+        &LaunchArgs {
+            door: Some(name),
+            raw: args.raw,
+            no_raw: args.no_raw,
+            ..Default::default()
+        },
+        config,
+    )
+}
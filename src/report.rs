@@ -0,0 +1,193 @@
+use super::charset;
+use super::config::{self, Capability};
+use super::history::{self, HistoryEntry};
+use super::list;
+use super::statsdb;
+use super::{Charset, OutputFormat, StatsArgs};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Local, Timelike, Utc};
+use comfy_table::Cell;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize, Debug)]
+pub struct Total {
+    pub name: String,
+    pub sessions: u64,
+    pub minutes: u64,
+    pub average_minutes: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Report {
+    pub doors: Vec<Total>,
+    pub users: Vec<Total>,
+    pub hours: Vec<Total>,
+}
+
+/// A Total with the section it came from, since CSV can't nest
+#[derive(Serialize, Debug)]
+struct CsvRow<'a> {
+    section: &'a str,
+    name: &'a str,
+    sessions: u64,
+    minutes: u64,
+    average_minutes: u64,
+}
+
+/// Finished sessions, from the stats database if it's turned on, otherwise the history
+pub fn sessions(config: &config::Config) -> Result<Vec<HistoryEntry>> {
+    if config.stats_db {
+        statsdb::sessions(config)
+    } else {
+        history::read(config)
+    }
+}
+
+fn minutes(entry: &HistoryEntry) -> u64 {
+    entry
+        .end
+        .signed_duration_since(entry.start)
+        .num_minutes()
+        .max(0) as u64
+}
+
+/// Adds up sessions by key, busiest first
+fn totals(entries: &[HistoryEntry], key: impl Fn(&HistoryEntry) -> String) -> Vec<Total> {
+    let mut sums: HashMap<String, (u64, u64)> = HashMap::new();
+
+    for entry in entries {
+        let sum = sums.entry(key(entry)).or_default();
+
+        sum.0 += 1;
+        sum.1 += minutes(entry);
+    }
+
+    let mut totals: Vec<Total> = sums
+        .into_iter()
+        .map(|(name, (sessions, minutes))| Total {
+            name,
+            sessions,
+            minutes,
+            average_minutes: minutes / sessions,
+        })
+        .collect();
+
+    totals.sort_by(|a, b| {
+        b.sessions
+            .cmp(&a.sessions)
+            .then(b.minutes.cmp(&a.minutes))
+            .then(a.name.cmp(&b.name))
+    });
+
+    totals
+}
+
+/// Totals per door and per user, busiest first, and per starting hour in order
+pub fn report(entries: &[HistoryEntry]) -> Report {
+    let mut hours = totals(entries, |entry| {
+        format!("{:02}:00", DateTime::<Local>::from(entry.start).hour())
+    });
+
+    hours.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Report {
+        doors: totals(entries, |entry| entry.door.clone()),
+        users: totals(entries, |entry| entry.user.clone()),
+        hours,
+    }
+}
+
+fn csv_rows(report: &Report) -> Vec<CsvRow<'_>> {
+    [
+        ("door", &report.doors),
+        ("user", &report.users),
+        ("hour", &report.hours),
+    ]
+    .into_iter()
+    .flat_map(|(section, totals)| {
+        totals.iter().map(move |total| CsvRow {
+            section,
+            name: &total.name,
+            sessions: total.sessions,
+            minutes: total.minutes,
+            average_minutes: total.average_minutes,
+        })
+    })
+    .collect()
+}
+
+fn print_totals(charset: Charset, title: &str, totals: &[Total]) -> Result<()> {
+    let mut table = charset::new_table(charset);
+
+    table.set_header(vec![title, "Sessions", "Minutes", "Average"]);
+
+    for total in totals {
+        table.add_row(vec![
+            Cell::new(&total.name),
+            Cell::new(total.sessions),
+            Cell::new(total.minutes),
+            Cell::new(format!("{} min", total.average_minutes)),
+        ]);
+    }
+
+    charset::print_line(charset, &table.to_string())
+}
+
+pub fn stats_command(args: &StatsArgs, config: &config::Config) -> Result<()> {
+    let door = match &args.door {
+        Some(door) => Some(config.get_door(door)?.name),
+        None => None,
+    };
+
+    // Without the stats capability, people only get to see their own sessions
+    let user = if config.can(Capability::Stats) {
+        args.user.clone()
+    } else {
+        Some(config.user.username.clone())
+    };
+
+    let since = match args.days {
+        Some(days) => Some(
+            Duration::try_days(days as i64)
+                .and_then(|days| Utc::now().checked_sub_signed(days))
+                .ok_or(anyhow!("--days {} is too far back", days))?,
+        ),
+        None => None,
+    };
+
+    let entries: Vec<HistoryEntry> = sessions(config)?
+        .into_iter()
+        .filter(|entry| door.as_ref().is_none_or(|door| &entry.door == door))
+        .filter(|entry| user.as_ref().is_none_or(|user| &entry.user == user))
+        .filter(|entry| since.is_none_or(|since| entry.start >= since))
+        .collect();
+
+    let report = report(&entries);
+
+    if let Some(format) = &args.format {
+        println!(
+            "{}",
+            match format {
+                OutputFormat::Json => serde_json::to_string(&report)?,
+                OutputFormat::Yaml => serde_yaml::to_string(&report)?,
+                OutputFormat::Csv => list::to_csv(&csv_rows(&report))?,
+            }
+        );
+
+        return Ok(());
+    }
+
+    if report.doors.is_empty() {
+        println!("No sessions found.");
+        return Ok(());
+    }
+
+    let charset = charset::detect(None);
+
+    print_totals(charset, "Door", &report.doors)?;
+    println!();
+    print_totals(charset, "User", &report.users)?;
+    println!();
+    print_totals(charset, "Hour", &report.hours)
+}
@@ -0,0 +1,123 @@
+use super::config::Door;
+use anyhow::{anyhow, Result};
+use log::debug;
+use serde::Deserialize;
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+fn default_timeout() -> u64 {
+    3
+}
+
+/// Something outside doorman that a door needs to be working before anyone can play it
+#[derive(Deserialize, Debug, Clone)]
+pub struct Requirement {
+    /// A file or directory that has to exist
+    pub path: Option<PathBuf>,
+
+    /// A host:port that has to accept TCP connections, i.e. a league server
+    pub tcp: Option<String>,
+
+    /// A directory that has to be a mount point, i.e. a network share
+    pub mount: Option<PathBuf>,
+
+    #[serde(default = "default_timeout")]
+    /// Seconds to wait for a TCP connection. Defaults to 3.
+    pub timeout: u64,
+
+    /// What to tell players when the requirement isn't met
+    pub message: Option<String>,
+}
+
+fn is_mount_point(path: &Path) -> bool {
+    let path = path.canonicalize().unwrap_or(path.to_path_buf());
+    let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+
+    mounts.lines().any(|line| {
+        line.split_whitespace()
+            .nth(1)
+            .is_some_and(|mount_point| Path::new(&mount_point.replace("\\040", " ")) == path)
+    })
+}
+
+fn can_connect(address: &str, timeout: Duration) -> Result<()> {
+    let addresses = address
+        .to_socket_addrs()
+        .map_err(|err| anyhow!("{} can't be resolved ({})", address, err))?;
+
+    for socket_address in addresses {
+        debug!("Trying to connect to {}", socket_address);
+
+        if TcpStream::connect_timeout(&socket_address, timeout).is_ok() {
+            return Ok(());
+        }
+    }
+
+    Err(anyhow!("{} isn't reachable", address))
+}
+
+impl Requirement {
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+
+        if let Some(path) = &self.path {
+            parts.push(format!("path {}", path.display()));
+        }
+
+        if let Some(mount) = &self.mount {
+            parts.push(format!("mount {}", mount.display()));
+        }
+
+        if let Some(address) = &self.tcp {
+            parts.push(format!("tcp {}", address));
+        }
+
+        parts.join(", ")
+    }
+
+    pub fn check(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            if !path.exists() {
+                return Err(anyhow!("{} doesn't exist", path.display()));
+            }
+        }
+
+        if let Some(mount) = &self.mount {
+            if !is_mount_point(mount) {
+                return Err(anyhow!("{} isn't mounted", mount.display()));
+            }
+        }
+
+        if let Some(address) = &self.tcp {
+            can_connect(address, Duration::from_secs(self.timeout))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks everything the door requires, with a message for players if something's missing
+pub fn check_requirements(door: &Door) -> Result<()> {
+    for requirement in door.options.requires.iter() {
+        if let Err(err) = requirement.check() {
+            debug!("Requirement for {} not met: {:#}", door.name, err);
+
+            return Err(match &requirement.message {
+                Some(message) => anyhow!(
+                    "Sorry, {} isn't available right now. {}",
+                    door.name,
+                    message
+                ),
+                None => anyhow!(
+                    "Sorry, {} isn't available right now: {}. Please try again later.",
+                    door.name,
+                    err
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
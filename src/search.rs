@@ -0,0 +1,32 @@
+use super::config;
+use super::list::{self, DoorListing};
+use super::SearchArgs;
+use anyhow::Result;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+/// Best score for query against any of the door's name, description, category, or tags
+fn score(matcher: &SkimMatcherV2, door: &DoorListing, query: &str) -> Option<i64> {
+    std::iter::once(&door.name)
+        .chain(door.description.iter())
+        .chain(door.category.iter())
+        .chain(door.tags.iter())
+        .filter_map(|text| matcher.fuzzy_match(text, query))
+        .max()
+}
+
+pub fn search_command(args: &SearchArgs, config: &config::Config) -> Result<()> {
+    let query = args.query.join(" ");
+    let matcher = SkimMatcherV2::default().ignore_case();
+
+    let mut matches: Vec<(i64, DoorListing)> = list::list(&None, config)?
+        .into_iter()
+        .filter_map(|door| Some((score(&matcher, &door, &query)?, door)))
+        .collect();
+
+    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    let doors: Vec<DoorListing> = matches.into_iter().map(|(_, door)| door).collect();
+
+    list::print_list(&args.format, &doors)
+}
@@ -0,0 +1,160 @@
+use super::config;
+use super::who::{self, WhoNode};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::debug;
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// What launch knows about a running session, kept in rundir so who can manage
+/// without the container engine
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SessionState {
+    pub container_id: String,
+    pub user: String,
+
+    #[serde(default)]
+    /// The player's uid, for hooks
+    pub uid: Option<u32>,
+
+    pub display_name: Option<String>,
+    pub door: String,
+    pub node: i8,
+    pub source: Option<String>,
+    pub impersonated_by: Option<String>,
+    pub since: DateTime<Utc>,
+
+    /// doorman process watching the session, which sticks around until the
+    /// container exits, whether or not the player is still attached
+    pub pid: u32,
+
+    /// The node's rundir, mounted at /mnt/doorman
+    pub rundir: PathBuf,
+
+    #[serde(default)]
+    /// Minutes the session may last
+    pub time_limit: Option<u32>,
+}
+impl SessionState {
+    pub fn read(config: &config::Config, door_name: &str, node: i8) -> Result<SessionState> {
+        let path = state_path(config, door_name, node);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read session state {}", path.display()))?;
+
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Couldn't parse session state {}", path.display()))
+    }
+
+    pub fn write(&self, config: &config::Config) -> Result<()> {
+        let path = state_path(config, &self.door, self.node);
+
+        fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("Couldn't write session state {}", path.display()))
+    }
+
+    /// Whether the session is still going, as far as the process watching it knows;
+    /// if that's gone, the state file was left behind by a crash
+    pub fn running(&self) -> bool {
+        alive(self.pid)
+    }
+}
+impl From<SessionState> for WhoNode {
+    fn from(state: SessionState) -> Self {
+        WhoNode {
+            container_id: state.container_id,
+            user: state.user,
+            display_name: state.display_name,
+            door: state.door,
+            node: Some(state.node),
+            command: None,
+            health: None,
+            impersonated_by: state.impersonated_by,
+            nowait: None,
+            source: state.source,
+            time_limit: state.time_limit,
+            time_left: who::time_left(state.since, state.time_limit),
+            since: state.since,
+        }
+    }
+}
+
+/// Whether process pid still exists
+pub fn alive(pid: u32) -> bool {
+    match kill(Pid::from_raw(pid as i32), None) {
+        Ok(()) | Err(Errno::EPERM) => true,
+        Err(_) => false,
+    }
+}
+
+pub fn state_path(config: &config::Config, door_name: &str, node: i8) -> PathBuf {
+    config.rundir.join(format!("{}.{}.json", door_name, node))
+}
+
+/// Removes the state file for node of door, unless it belongs to a newer session
+/// than the one in container_id
+pub fn remove(
+    config: &config::Config,
+    door_name: &str,
+    node: i8,
+    container_id: &str,
+) -> Result<()> {
+    let path = state_path(config, door_name, node);
+
+    if SessionState::read(config, door_name, node)
+        .is_ok_and(|state| state.container_id == container_id)
+    {
+        fs::remove_file(&path)
+            .with_context(|| format!("Couldn't remove session state {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Sessions with state files in rundir, with the path of each file, optionally only
+/// those of one door
+pub fn session_files(
+    config: &config::Config,
+    door: &Option<String>,
+) -> Result<Vec<(PathBuf, SessionState)>> {
+    let mut sessions: Vec<(PathBuf, SessionState)> = vec![];
+
+    for entry in fs::read_dir(&config.rundir)
+        .with_context(|| format!("Couldn't read rundir {}", config.rundir.display()))?
+    {
+        let path = entry?.path();
+
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+
+        let state: SessionState = match fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| Ok(serde_json::from_str(&contents)?))
+        {
+            Ok(state) => state,
+            Err(err) => {
+                debug!("Skipping {}: {:#}", path.display(), err);
+                continue;
+            }
+        };
+
+        if door.as_ref().is_none_or(|door| &state.door == door) {
+            sessions.push((path, state));
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Sessions that are still going, optionally only those of one door
+pub fn sessions(config: &config::Config, door: &Option<String>) -> Result<Vec<SessionState>> {
+    Ok(session_files(config, door)?
+        .into_iter()
+        .map(|(_, state)| state)
+        .filter(SessionState::running)
+        .collect())
+}
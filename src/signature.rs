@@ -0,0 +1,155 @@
+use super::checksum;
+use super::config;
+use super::update::{self, ImageReference};
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// How to check image signatures with cosign before running them
+#[derive(Deserialize, Debug, Clone)]
+pub struct CosignOptions {
+    /// Path to the cosign binary; defaults to cosign on $PATH
+    pub path: Option<PathBuf>,
+
+    /// Public key images have to be signed with
+    pub key: Option<PathBuf>,
+
+    /// Identity in the signing certificate, for keyless signing,
+    /// i.e. the URL of the workflow that built the image
+    pub certificate_identity: Option<String>,
+
+    /// OIDC issuer of the signing certificate, for keyless signing,
+    /// i.e. https://token.actions.githubusercontent.com
+    pub certificate_oidc_issuer: Option<String>,
+}
+
+impl CosignOptions {
+    fn command(&self, reference: &str) -> Result<Command> {
+        let mut cmd = Command::new(self.path.clone().unwrap_or(PathBuf::from("cosign")));
+
+        cmd.arg("verify");
+
+        match (
+            &self.key,
+            &self.certificate_identity,
+            &self.certificate_oidc_issuer,
+        ) {
+            (Some(key), _, _) => {
+                cmd.arg(format!("--key={}", key.display()));
+            }
+            (None, Some(identity), Some(issuer)) => {
+                cmd.arg(format!("--certificate-identity={}", identity))
+                    .arg(format!("--certificate-oidc-issuer={}", issuer));
+            }
+            _ => {
+                return Err(anyhow!(
+                    "cosign needs either key, or certificate_identity and certificate_oidc_issuer"
+                ))
+            }
+        }
+
+        cmd.arg(reference);
+
+        Ok(cmd)
+    }
+
+    /// Who images have to be signed by, so that changing the key or identity
+    /// means checking images again
+    fn signer(&self) -> Result<String> {
+        match (
+            &self.key,
+            &self.certificate_identity,
+            &self.certificate_oidc_issuer,
+        ) {
+            (Some(key), _, _) => Ok(format!("key:{}", checksum::sha256_file(key)?)),
+            (None, Some(identity), Some(issuer)) => {
+                Ok(format!("identity:{} issuer:{}", identity, issuer))
+            }
+            _ => Err(anyhow!(
+                "cosign needs either key, or certificate_identity and certificate_oidc_issuer"
+            )),
+        }
+    }
+}
+
+/// Digests that have already passed a signature check, along with who they were
+/// signed by, so launches don't have to wait on cosign
+fn verified_path(config: &config::Config) -> PathBuf {
+    config.datadir.join("verified-digests")
+}
+
+fn verified_line(digest: &str, signer: &str) -> String {
+    format!("{} {}", digest, signer)
+}
+
+fn already_verified(config: &config::Config, digest: &str, signer: &str) -> bool {
+    let wanted = verified_line(digest, signer);
+
+    fs::read_to_string(verified_path(config))
+        .map(|verified| verified.lines().any(|line| line == wanted))
+        .unwrap_or(false)
+}
+
+fn record_verified(config: &config::Config, digest: &str, signer: &str) -> Result<()> {
+    let path = verified_path(config);
+    let mut verified = fs::read_to_string(&path).unwrap_or_default();
+
+    verified.push_str(&verified_line(digest, signer));
+    verified.push('\n');
+
+    fs::write(&path, verified).with_context(|| format!("Couldn't write {}", path.display()))
+}
+
+/// Checks that image is pinned by digest if the config requires it, and that
+/// the digest that's actually going to run is signed, if cosign is configured
+pub fn check_image(config: &config::Config, image: &str) -> Result<()> {
+    let reference = ImageReference::parse(image);
+
+    if config.require_digest && reference.digest.is_none() {
+        return Err(anyhow!(
+            "{} isn't pinned by digest; use {}@sha256:...",
+            image,
+            image
+        ));
+    }
+
+    let Some(cosign) = &config.cosign else {
+        return Ok(());
+    };
+
+    // Verify what's been pulled, not whatever a tag points at now
+    let digest = match &reference.digest {
+        Some(digest) => digest.clone(),
+        None => update::local_digests(image, config)?
+            .into_iter()
+            .next()
+            .ok_or(anyhow!("Couldn't find the digest of {}", image))?,
+    };
+
+    let signer = cosign.signer()?;
+
+    if already_verified(config, &digest, &signer) {
+        debug!("{} was already verified", digest);
+        return Ok(());
+    }
+
+    let pinned = format!("{}/{}@{}", reference.registry, reference.repository, digest);
+    let output = cosign
+        .command(&pinned)?
+        .stdout(Stdio::null())
+        .output()
+        .with_context(|| "Couldn't run cosign")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Signature check failed for {}: {}",
+            pinned,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    record_verified(config, &digest, &signer)
+}
@@ -0,0 +1,115 @@
+use super::charset;
+use super::config::{self, Capability};
+use super::door;
+use super::{Charset, SimulateArgs};
+use anyhow::{anyhow, Result};
+use comfy_table::Cell;
+use std::thread;
+use std::time::{Duration, Instant};
+
+struct Outcome {
+    node: Option<i8>,
+    waited: Duration,
+    error: Option<String>,
+}
+
+/// Goes through the same locking as a real launch, then holds the node for duration
+fn simulate_session(config: &config::Config, door: &config::Door, duration: Duration) -> Outcome {
+    let started = Instant::now();
+    let result = door::make_lockfile(&config.rundir.join(format!("{}.lock", door.name))).and_then(
+        |door_lockfile| {
+            if door_lockfile.try_lock_shared().is_err() {
+                return Err(anyhow!("{} is undergoing maintenence", door.name));
+            }
+
+            let (node, _, node_lockfile) = door::make_node_lockfile(door, config)?;
+
+            Ok((node, door_lockfile, node_lockfile))
+        },
+    );
+    let waited = started.elapsed();
+
+    match result {
+        Ok((node, _door_lockfile, _node_lockfile)) => {
+            thread::sleep(duration);
+
+            Outcome {
+                node: Some(node),
+                waited,
+                error: None,
+            }
+        }
+        Err(err) => Outcome {
+            node: None,
+            waited,
+            error: Some(format!("{:#}", err)),
+        },
+    }
+}
+
+pub fn simulate_command(args: &SimulateArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Nightly) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let door = config.get_door(&args.door)?;
+    let duration = Duration::from_secs(args.duration);
+
+    println!(
+        "Simulating {} sessions of {} for {} seconds; real players may find it busy meanwhile.",
+        args.sessions, door.name, args.duration
+    );
+
+    let outcomes: Vec<Outcome> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..args.sessions)
+            .map(|_| scope.spawn(|| simulate_session(config, &door, duration)))
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Simulated session panicked"))
+            .collect()
+    });
+
+    let mut table = charset::new_table(Charset::Utf8);
+
+    table.set_header(vec!["Session", "Node", "Lock time", "Result"]);
+
+    for (index, outcome) in outcomes.iter().enumerate() {
+        table.add_row(vec![
+            Cell::new(index + 1),
+            Cell::new(
+                outcome
+                    .node
+                    .map_or("-".to_string(), |node| node.to_string()),
+            ),
+            Cell::new(format!("{} ms", outcome.waited.as_millis())),
+            Cell::new(outcome.error.as_deref().unwrap_or("played")),
+        ]);
+    }
+
+    println!("{}", table);
+
+    let played = outcomes
+        .iter()
+        .filter(|outcome| outcome.node.is_some())
+        .count();
+    let mut nodes: Vec<i8> = outcomes.iter().filter_map(|outcome| outcome.node).collect();
+
+    nodes.sort();
+    nodes.dedup();
+
+    println!(
+        "{} of {} sessions got a node; {} has {} nodes.",
+        played,
+        args.sessions,
+        door.name,
+        door.options.node_numbers()?.len()
+    );
+
+    if nodes.len() != played {
+        return Err(anyhow!("Two sessions were given the same node!"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use super::config;
+use super::dos::Templates;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
+use fs4::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::PathBuf;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DoorStats {
+    pub plays: u64,
+    pub last_player: Option<String>,
+    pub last_played: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub nightly_failures: u32,
+
+    #[serde(default)]
+    pub last_nightly: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub last_session_end: Option<DateTime<Utc>>,
+}
+impl DoorStats {
+    /// When someone last started or finished playing
+    pub fn last_activity(&self) -> Option<DateTime<Utc>> {
+        self.last_played.max(self.last_session_end)
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct DoorStatsVars<'a> {
+    door: &'a str,
+    plays: u64,
+    one_play: bool,
+    last_player: Option<&'a str>,
+    last_played: Option<String>,
+}
+
+fn stats_path(config: &config::Config, door: &config::Door) -> PathBuf {
+    config
+        .datadir
+        .join("stats")
+        .join(format!("{}.json", door.name))
+}
+
+fn open_stats(config: &config::Config, door: &config::Door) -> Result<fs::File> {
+    let path = stats_path(config, door);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Couldn't create stats dir {}", parent.display()))?;
+    }
+
+    fs::File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .with_context(|| format!("Couldn't open stats file {}", path.display()))
+}
+
+fn read_stats(file: &mut fs::File) -> Result<DoorStats> {
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    if contents.trim().is_empty() {
+        return Ok(DoorStats::default());
+    }
+
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// How many nightly runs in a row have failed for door; reading it doesn't create
+/// the stats file, so listing doors has no side effects
+pub fn nightly_failures(config: &config::Config, door: &config::Door) -> u32 {
+    fs::read_to_string(stats_path(config, door))
+        .ok()
+        .and_then(|contents| serde_json::from_str::<DoorStats>(&contents).ok())
+        .map_or(0, |stats| stats.nightly_failures)
+}
+
+pub fn load(config: &config::Config, door: &config::Door) -> Result<DoorStats> {
+    let mut file = open_stats(config, door)?;
+
+    file.lock_shared()?;
+    read_stats(&mut file)
+}
+
+fn update<F: FnOnce(&mut DoorStats)>(
+    config: &config::Config,
+    door: &config::Door,
+    change: F,
+) -> Result<()> {
+    let mut file = open_stats(config, door)?;
+
+    file.lock_exclusive()?;
+
+    let mut stats = read_stats(&mut file)?;
+
+    change(&mut stats);
+
+    file.rewind()?;
+    file.set_len(0)?;
+    file.write_all(serde_json::to_string(&stats)?.as_bytes())?;
+
+    Ok(())
+}
+
+pub fn record_play(config: &config::Config, door: &config::Door) -> Result<()> {
+    update(config, door, |stats| {
+        stats.plays += 1;
+        stats.last_player = Some(config.user.display_name.clone());
+        stats.last_played = Some(Utc::now());
+    })
+}
+
+pub fn record_session_end(config: &config::Config, door: &config::Door) -> Result<()> {
+    update(config, door, |stats| {
+        stats.last_session_end = Some(Utc::now());
+    })
+}
+
+pub fn record_nightly(config: &config::Config, door: &config::Door, success: bool) -> Result<()> {
+    update(config, door, |stats| {
+        if success {
+            stats.nightly_failures = 0;
+        } else {
+            stats.nightly_failures += 1;
+        }
+
+        stats.last_nightly = Some(Utc::now());
+    })
+}
+
+pub fn write_door_stats(
+    config: &config::Config,
+    door: &config::Door,
+    templates: &Templates,
+) -> Result<()> {
+    let Some(stats_file) = &door.options.stats_file else {
+        return Ok(());
+    };
+
+    let stats = load(config, door)?;
+
+    let vars = DoorStatsVars {
+        door: &door.name,
+        plays: stats.plays,
+        one_play: stats.plays == 1,
+        last_player: stats.last_player.as_deref(),
+        last_played: stats.last_played.map(|last_played| {
+            DateTime::<Local>::from(last_played)
+                .format("%m/%d/%y")
+                .to_string()
+        }),
+    };
+
+    templates
+        .write_dos_as("doorstat.txt", stats_file, &door.options.door_path, &vars)
+        .with_context(|| format!("Couldn't write stats file for {}", door.name))
+}
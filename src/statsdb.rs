@@ -0,0 +1,277 @@
+use super::charset;
+use super::config::{self, Capability};
+use super::history::{self, HistoryEntry};
+use super::list;
+use super::{OutputFormat, StatsDbQueryArgs};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use comfy_table::Cell;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    door TEXT NOT NULL,
+    user TEXT NOT NULL,
+    node INTEGER NOT NULL,
+    start INTEGER NOT NULL,
+    end INTEGER NOT NULL,
+    exit_code INTEGER,
+    UNIQUE (door, user, node, start)
+);
+CREATE INDEX IF NOT EXISTS sessions_start ON sessions (start);
+";
+
+#[derive(Serialize, Debug)]
+pub struct MonthlyTotal {
+    pub month: String,
+    pub door: String,
+    pub sessions: u64,
+    pub minutes: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HourlyTotal {
+    pub hour: u32,
+    pub sessions: u64,
+    pub minutes: u64,
+}
+
+fn db_path(config: &config::Config) -> PathBuf {
+    config.datadir.join("stats.db")
+}
+
+pub fn open(config: &config::Config) -> Result<Connection> {
+    let path = db_path(config);
+    let db = Connection::open(&path)
+        .with_context(|| format!("Couldn't open stats database {}", path.display()))?;
+
+    // Sessions on other nodes may be writing at the same time
+    db.busy_timeout(Duration::from_secs(5))?;
+    db.execute_batch(SCHEMA)
+        .with_context(|| format!("Couldn't set up stats database {}", path.display()))?;
+
+    Ok(db)
+}
+
+fn insert(db: &Connection, entry: &HistoryEntry) -> Result<usize> {
+    Ok(db.execute(
+        "INSERT OR IGNORE INTO sessions (door, user, node, start, end, exit_code)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            entry.door,
+            entry.user,
+            entry.node,
+            entry.start.timestamp(),
+            entry.end.timestamp(),
+            entry.exit_code
+        ],
+    )?)
+}
+
+/// Adds a finished session to the database, if stats_db is turned on
+pub fn record(config: &config::Config, entry: &HistoryEntry) -> Result<()> {
+    if !config.stats_db {
+        return Ok(());
+    }
+
+    insert(&open(config)?, entry)?;
+
+    Ok(())
+}
+
+/// Copies sessions from history.jsonl that the database doesn't have yet
+fn import(config: &config::Config) -> Result<usize> {
+    let mut db = open(config)?;
+    let tx = db.transaction()?;
+    let mut imported = 0;
+
+    for entry in history::read(config)? {
+        imported += insert(&tx, &entry)?;
+    }
+
+    tx.commit()?;
+
+    Ok(imported)
+}
+
+/// Every session in the database, oldest first
+pub fn sessions(config: &config::Config) -> Result<Vec<HistoryEntry>> {
+    let db = open(config)?;
+    let mut query =
+        db.prepare("SELECT door, user, node, start, end, exit_code FROM sessions ORDER BY start")?;
+
+    let sessions = query
+        .query_map([], |row| {
+            Ok(HistoryEntry {
+                user: row.get(1)?,
+                display_name: None,
+                door: row.get(0)?,
+                node: row.get(2)?,
+                start: timestamp(row.get(3)?),
+                end: timestamp(row.get(4)?),
+                exit_code: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<HistoryEntry>>>()?;
+
+    Ok(sessions)
+}
+
+fn timestamp(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+}
+
+pub fn monthly(config: &config::Config, door: &Option<String>) -> Result<Vec<MonthlyTotal>> {
+    let db = open(config)?;
+    let mut query = db.prepare(
+        "SELECT strftime('%Y-%m', start, 'unixepoch', 'localtime') AS month, door,
+                COUNT(*), SUM(end - start) / 60
+         FROM sessions
+         WHERE ?1 IS NULL OR door = ?1
+         GROUP BY month, door
+         ORDER BY month DESC, COUNT(*) DESC",
+    )?;
+
+    let totals = query
+        .query_map(params![door], |row| {
+            Ok(MonthlyTotal {
+                month: row.get(0)?,
+                door: row.get(1)?,
+                sessions: row.get(2)?,
+                minutes: row.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<MonthlyTotal>>>()?;
+
+    Ok(totals)
+}
+
+pub fn hourly(config: &config::Config, door: &Option<String>) -> Result<Vec<HourlyTotal>> {
+    let db = open(config)?;
+    let mut query = db.prepare(
+        "SELECT CAST(strftime('%H', start, 'unixepoch', 'localtime') AS INTEGER) AS hour,
+                COUNT(*), SUM(end - start) / 60
+         FROM sessions
+         WHERE ?1 IS NULL OR door = ?1
+         GROUP BY hour
+         ORDER BY hour",
+    )?;
+
+    let totals = query
+        .query_map(params![door], |row| {
+            Ok(HourlyTotal {
+                hour: row.get(0)?,
+                sessions: row.get(1)?,
+                minutes: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<HourlyTotal>>>()?;
+
+    Ok(totals)
+}
+
+fn check_access(config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Stats) {
+        return Err(anyhow!("Only sysops can query the stats database!"));
+    }
+
+    if !config.stats_db {
+        return Err(anyhow!(
+            "The stats database is turned off; set stats_db: true under doorman to use it"
+        ));
+    }
+
+    Ok(())
+}
+
+fn query_door(args: &StatsDbQueryArgs, config: &config::Config) -> Result<Option<String>> {
+    match &args.door {
+        Some(door) => Ok(Some(config.get_door(door)?.name)),
+        None => Ok(None),
+    }
+}
+
+fn print_rows<T: Serialize>(
+    format: &Option<OutputFormat>,
+    rows: &[T],
+    header: Vec<&str>,
+    cells: impl Fn(&T) -> Vec<Cell>,
+) -> Result<()> {
+    if let Some(format) = format {
+        println!(
+            "{}",
+            match format {
+                OutputFormat::Json => serde_json::to_string(rows)?,
+                OutputFormat::Yaml => serde_yaml::to_string(rows)?,
+                OutputFormat::Csv => list::to_csv(rows)?,
+            }
+        );
+
+        return Ok(());
+    }
+
+    if rows.is_empty() {
+        println!("No sessions recorded yet.");
+        return Ok(());
+    }
+
+    let charset = charset::detect(None);
+    let mut table = charset::new_table(charset);
+
+    table.set_header(header);
+
+    for row in rows {
+        table.add_row(cells(row));
+    }
+
+    charset::print_line(charset, &table.to_string())
+}
+
+pub fn monthly_command(args: &StatsDbQueryArgs, config: &config::Config) -> Result<()> {
+    check_access(config)?;
+
+    print_rows(
+        &args.format,
+        &monthly(config, &query_door(args, config)?)?,
+        vec!["Month", "Door", "Sessions", "Minutes"],
+        |total| {
+            vec![
+                Cell::new(&total.month),
+                Cell::new(&total.door),
+                Cell::new(total.sessions),
+                Cell::new(total.minutes),
+            ]
+        },
+    )
+}
+
+pub fn hours_command(args: &StatsDbQueryArgs, config: &config::Config) -> Result<()> {
+    check_access(config)?;
+
+    print_rows(
+        &args.format,
+        &hourly(config, &query_door(args, config)?)?,
+        vec!["Hour", "Sessions", "Minutes"],
+        |total| {
+            vec![
+                Cell::new(format!("{:02}:00", total.hour)),
+                Cell::new(total.sessions),
+                Cell::new(total.minutes),
+            ]
+        },
+    )
+}
+
+pub fn import_command(config: &config::Config) -> Result<()> {
+    check_access(config)?;
+
+    let imported = import(config)?;
+
+    println!("Imported {} sessions from the history.", imported);
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anyhow::{anyhow, Context, Result};
+use nix::sys::statvfs::{statvfs, FsFlags};
+use nix::unistd::{access, AccessFlags};
+use std::path::Path;
+
+/// Less free space than this and lockfiles, drop files, and session logs start failing
+const MIN_FREE_BYTES: u64 = 1024 * 1024;
+
+/// Checks that doorman can write to dir, so launches fail with a clear message
+/// instead of a confusing IO error halfway through
+pub fn check_writable(dir: &Path, what: &str) -> Result<()> {
+    let stat =
+        statvfs(dir).with_context(|| format!("Couldn't check {} {}", what, dir.display()))?;
+
+    if stat.flags().contains(FsFlags::ST_RDONLY) {
+        return Err(anyhow!(
+            "The {} {} is on a read-only filesystem",
+            what,
+            dir.display()
+        ));
+    }
+
+    let free = stat.blocks_available() as u64 * stat.fragment_size() as u64;
+
+    if free < MIN_FREE_BYTES {
+        return Err(anyhow!(
+            "The {} {} is out of space ({} KB free); old session logs are a good place to start cleaning up",
+            what,
+            dir.display(),
+            free / 1024
+        ));
+    }
+
+    if access(dir, AccessFlags::W_OK).is_err() {
+        return Err(anyhow!(
+            "The {} {} isn't writable by this user",
+            what,
+            dir.display()
+        ));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use log::{debug, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where to send OpenTelemetry traces of launches and maintenence runs
+#[derive(Deserialize, Debug, Clone)]
+pub struct OtlpOptions {
+    /// Base URL of an OTLP/HTTP collector, i.e. http://localhost:4318
+    pub endpoint: String,
+
+    #[serde(default)]
+    /// Extra HTTP headers to send, i.e. for authentication
+    pub headers: HashMap<String, String>,
+
+    #[serde(default = "default_service_name")]
+    /// service.name to report. Defaults to doorman.
+    pub service_name: String,
+}
+
+fn default_service_name() -> String {
+    "doorman".to_string()
+}
+
+struct Span {
+    span_id: String,
+    parent: Option<usize>,
+    name: String,
+    start: SystemTime,
+    end: Option<SystemTime>,
+    attributes: Vec<(String, String)>,
+    events: Vec<(String, SystemTime, String)>,
+    error: Option<String>,
+}
+
+/// A span in a Trace
+#[derive(Clone, Copy, Debug)]
+pub struct SpanId(usize);
+
+/// Spans for one doorman run, sent to the collector all at once by export
+pub struct Trace {
+    otlp: Option<OtlpOptions>,
+    trace_id: String,
+    spans: Vec<Span>,
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({"key": key, "value": {"stringValue": value}})
+}
+
+impl Trace {
+    /// Spans are only kept if otlp is set
+    pub fn new(otlp: &Option<OtlpOptions>) -> Trace {
+        Trace {
+            otlp: otlp.clone(),
+            trace_id: format!("{:032x}", fastrand::u128(1..)),
+            spans: vec![],
+        }
+    }
+
+    pub fn start(&mut self, name: &str, parent: Option<SpanId>) -> SpanId {
+        if self.otlp.is_some() {
+            self.spans.push(Span {
+                span_id: format!("{:016x}", fastrand::u64(1..)),
+                parent: parent.map(|parent| parent.0),
+                name: name.to_string(),
+                start: SystemTime::now(),
+                end: None,
+                attributes: vec![],
+                events: vec![],
+                error: None,
+            });
+        }
+
+        SpanId(self.spans.len().saturating_sub(1))
+    }
+
+    pub fn attribute(&mut self, span: SpanId, key: &str, value: impl ToString) {
+        if let Some(span) = self.spans.get_mut(span.0) {
+            span.attributes.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    /// Records something that happened during span, i.e. a problem worth alerting on
+    pub fn event(&mut self, span: SpanId, name: &str, message: &str) {
+        if let Some(span) = self.spans.get_mut(span.0) {
+            span.events
+                .push((name.to_string(), SystemTime::now(), message.to_string()));
+        }
+    }
+
+    pub fn end(&mut self, span: SpanId) {
+        if let Some(span) = self.spans.get_mut(span.0) {
+            span.end.get_or_insert_with(SystemTime::now);
+        }
+    }
+
+    /// Ends span, marking it as failed if result is an error
+    pub fn finish<T>(&mut self, span: SpanId, result: &Result<T>) {
+        if let (Err(err), Some(span)) = (result, self.spans.get_mut(span.0)) {
+            span.error = Some(format!("{:#}", err));
+        }
+
+        self.end(span);
+    }
+
+    fn to_json(&self, otlp: &OtlpOptions) -> Value {
+        let now = SystemTime::now();
+        let spans: Vec<Value> = self
+            .spans
+            .iter()
+            .map(|span| {
+                let mut value = json!({
+                    "traceId": self.trace_id,
+                    "spanId": span.span_id,
+                    "name": span.name,
+                    "kind": 1,
+                    "startTimeUnixNano": unix_nanos(span.start),
+                    // Spans cut short by an error end when the trace is sent
+                    "endTimeUnixNano": unix_nanos(span.end.unwrap_or(now)),
+                    "attributes": span
+                        .attributes
+                        .iter()
+                        .map(|(key, value)| attribute(key, value))
+                        .collect::<Vec<Value>>(),
+                    "events": span
+                        .events
+                        .iter()
+                        .map(|(name, time, message)| json!({
+                            "name": name,
+                            "timeUnixNano": unix_nanos(*time),
+                            "attributes": [attribute("message", message)],
+                        }))
+                        .collect::<Vec<Value>>(),
+                    "status": match &span.error {
+                        Some(message) => json!({"code": 2, "message": message}),
+                        None => json!({"code": 1}),
+                    },
+                });
+
+                if let Some(parent) = span.parent.and_then(|parent| self.spans.get(parent)) {
+                    value["parentSpanId"] = json!(parent.span_id);
+                }
+
+                value
+            })
+            .collect();
+
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", &otlp.service_name)],
+                },
+                "scopeSpans": [{
+                    "scope": {"name": "doorman", "version": env!("CARGO_PKG_VERSION")},
+                    "spans": spans,
+                }],
+            }],
+        })
+    }
+
+    /// Sends the trace to the collector; failures are only logged, never fatal
+    pub fn export(&self) {
+        let Some(otlp) = &self.otlp else {
+            return;
+        };
+
+        let url = format!("{}/v1/traces", otlp.endpoint.trim_end_matches('/'));
+        let mut request = ureq::post(&url)
+            .timeout(Duration::from_secs(2))
+            .set("Content-Type", "application/json");
+
+        for (name, value) in otlp.headers.iter() {
+            request = request.set(name, value);
+        }
+
+        debug!("Sending {} spans to {}", self.spans.len(), url);
+
+        if let Err(err) = request.send_string(&self.to_json(otlp).to_string()) {
+            warn!("Couldn't send trace to {}: {}", url, err);
+        }
+    }
+}
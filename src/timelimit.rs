@@ -0,0 +1,9 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Minutes before a session is ended to warn the player
+pub const WARNINGS: [i64; 2] = [5, 1];
+
+/// When a session that started at since runs out of time
+pub fn deadline(since: DateTime<Utc>, limit: Option<u32>) -> Option<DateTime<Utc>> {
+    limit.map(|minutes| since + Duration::minutes(minutes.into()))
+}
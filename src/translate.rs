@@ -0,0 +1,179 @@
+use super::dos::Codepage;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Name of the translation table in the node rundir
+pub const TRANSLATE_FILE: &str = "translate.json";
+
+/// Tweaks to the ANSI+CP437 translation, for doors that emit odd glyphs or
+/// sequences that garble modern terminals
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Translation {
+    #[serde(default)]
+    /// CP437 characters to show as something else, keyed by hex value, i.e. 0E: "♫"
+    pub glyphs: BTreeMap<String, String>,
+
+    #[serde(default)]
+    /// Escape sequences to drop from the door's output, i.e. "\e[6n"
+    pub strip: Vec<String>,
+}
+
+/// The table as kept in the node rundir, so reattaching translates the same way
+#[derive(Serialize, Deserialize, Debug)]
+struct TranslationFile {
+    glyphs: BTreeMap<u8, String>,
+    strip: Vec<String>,
+}
+
+impl Translation {
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty() && self.strip.is_empty()
+    }
+
+    fn glyphs(&self) -> Result<BTreeMap<u8, String>> {
+        self.glyphs
+            .iter()
+            .map(|(key, glyph)| {
+                let hex = key.trim_start_matches("0x").trim_start_matches("0X");
+
+                u8::from_str_radix(hex, 16)
+                    .map(|byte| (byte, glyph.clone()))
+                    .map_err(|_| {
+                        anyhow!(
+                            "Bad glyph '{}' in translate; use the character's hex value, i.e. 0E",
+                            key
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    /// Checks that the table makes sense, without writing it anywhere
+    pub fn check(&self) -> Result<()> {
+        self.glyphs()?;
+        Ok(())
+    }
+
+    /// Writes the table to the node rundir dir for the session to use
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(TRANSLATE_FILE);
+        let file = TranslationFile {
+            glyphs: self.glyphs()?,
+            strip: self.strip.clone(),
+        };
+
+        fs::write(&path, serde_json::to_string(&file)?)
+            .with_context(|| format!("Couldn't write {}", path.display()))
+    }
+}
+
+/// Applies a translation table to the door's output as it goes by
+pub struct Translator {
+    /// What to look for in the output and what to put in its place, longest first
+    replacements: Vec<(Vec<u8>, Vec<u8>)>,
+
+    /// The start of something that might need replacing, held until we see the rest
+    pending: Vec<u8>,
+}
+impl Translator {
+    /// The translator for the session in the node rundir dir, if it has a table
+    pub fn load(dir: &Path) -> Result<Option<Translator>> {
+        let path = dir.join(TRANSLATE_FILE);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read {}", path.display()))?;
+        let file: TranslationFile = serde_json::from_str(&contents)
+            .with_context(|| format!("Couldn't parse {}", path.display()))?;
+
+        // The door's output has already been turned into UTF-8 by the time we see it
+        let glyphs = file.glyphs.into_iter().map(|(byte, glyph)| {
+            (
+                Codepage::Cp437.decode(&[byte]).into_bytes(),
+                glyph.into_bytes(),
+            )
+        });
+        let strip = file
+            .strip
+            .into_iter()
+            .map(|sequence| (sequence.into_bytes(), vec![]));
+
+        let mut replacements: Vec<(Vec<u8>, Vec<u8>)> = glyphs
+            .chain(strip)
+            .filter(|(from, _)| !from.is_empty())
+            .collect();
+
+        replacements.sort_by_key(|(from, _)| Reverse(from.len()));
+
+        Ok(Some(Translator {
+            replacements,
+            pending: vec![],
+        }))
+    }
+
+    /// Translates input onto output, holding back anything that could be the start
+    /// of a replacement until the next call
+    fn translate(&mut self, input: &[u8], output: &mut Vec<u8>) {
+        self.pending.extend_from_slice(input);
+
+        let mut pos = 0;
+
+        'next: while pos < self.pending.len() {
+            let rest = &self.pending[pos..];
+
+            for (from, to) in &self.replacements {
+                if rest.starts_with(from) {
+                    output.extend_from_slice(to);
+                    pos += from.len();
+                    continue 'next;
+                }
+            }
+
+            if self
+                .replacements
+                .iter()
+                .any(|(from, _)| from.starts_with(rest))
+            {
+                break;
+            }
+
+            output.push(rest[0]);
+            pos += 1;
+        }
+
+        self.pending.drain(..pos);
+    }
+
+    /// Copies input to output until input runs out, translating along the way
+    pub fn copy(mut self, input: &mut impl Read, output: &mut impl Write) -> Result<()> {
+        let mut buffer = [0u8; 4096];
+        let mut translated = vec![];
+
+        loop {
+            let count = match input.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(count) => count,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err).context("Couldn't read the door's output"),
+            };
+
+            translated.clear();
+            self.translate(&buffer[..count], &mut translated);
+            output.write_all(&translated)?;
+            output.flush()?;
+        }
+
+        output.write_all(&self.pending)?;
+        output.flush()?;
+
+        Ok(())
+    }
+}
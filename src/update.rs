@@ -0,0 +1,268 @@
+use super::config::{self, Capability};
+use super::pull;
+use super::webhook::{self, WebhookEvent};
+use super::CheckUpdatesArgs;
+use anyhow::{anyhow, Context, Result};
+use log::{debug, info};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const MANIFEST_TYPES: &str = "application/vnd.oci.image.index.v1+json, \
+    application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+pub struct ImageReference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: Option<String>,
+    pub digest: Option<String>,
+}
+impl ImageReference {
+    pub fn parse(image: &str) -> ImageReference {
+        let (name, digest) = match image.split_once('@') {
+            Some((name, digest)) => (name, Some(digest.to_string())),
+            None => (image, None),
+        };
+
+        let (name, tag) = match name.rfind(':') {
+            Some(colon) if !name[colon..].contains('/') => {
+                (&name[..colon], Some(name[colon + 1..].to_string()))
+            }
+            _ => (name, None),
+        };
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, rest))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), rest.to_string())
+            }
+            Some(_) => ("docker.io".to_string(), name.to_string()),
+            None => ("docker.io".to_string(), format!("library/{}", name)),
+        };
+
+        let tag = if tag.is_none() && digest.is_none() {
+            Some("latest".to_string())
+        } else {
+            tag
+        };
+
+        ImageReference {
+            registry,
+            repository,
+            tag,
+            digest,
+        }
+    }
+
+    fn api_host(&self) -> &str {
+        if self.registry == "docker.io" {
+            "registry-1.docker.io"
+        } else {
+            &self.registry
+        }
+    }
+}
+
+fn parse_challenge(header: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let header = header.trim();
+    let header = header
+        .strip_prefix("Bearer ")
+        .or_else(|| header.strip_prefix("bearer "))
+        .unwrap_or(header);
+
+    let mut key = String::new();
+    let mut value = String::new();
+    let mut in_value = false;
+    let mut in_quotes = false;
+
+    for c in header.chars() {
+        match c {
+            '"' if in_value => in_quotes = !in_quotes,
+            '=' if !in_value => in_value = true,
+            ',' if !in_quotes => {
+                params.insert(key.trim().to_string(), value.clone());
+                key.clear();
+                value.clear();
+                in_value = false;
+            }
+            _ if in_value => value.push(c),
+            _ => key.push(c),
+        }
+    }
+
+    if !key.trim().is_empty() {
+        params.insert(key.trim().to_string(), value);
+    }
+
+    params
+}
+
+fn fetch_token(challenge: &str) -> Result<String> {
+    let params = parse_challenge(challenge);
+    let realm = params
+        .get("realm")
+        .ok_or(anyhow!("Registry didn't say where to get a token"))?;
+
+    let mut request = ureq::get(realm);
+
+    for key in ["service", "scope"] {
+        if let Some(value) = params.get(key) {
+            request = request.query(key, value);
+        }
+    }
+
+    let response: TokenResponse = serde_json::from_reader(request.call()?.into_reader())
+        .with_context(|| "While parsing registry token")?;
+
+    response
+        .token
+        .or(response.access_token)
+        .ok_or(anyhow!("Registry didn't return a token"))
+}
+
+pub fn remote_digest(image: &ImageReference) -> Result<String> {
+    let reference = image
+        .digest
+        .clone()
+        .or(image.tag.clone())
+        .unwrap_or("latest".to_string());
+
+    let url = format!(
+        "https://{}/v2/{}/manifests/{}",
+        image.api_host(),
+        image.repository,
+        reference
+    );
+
+    debug!("Checking {}", url);
+
+    let response = match ureq::head(&url).set("Accept", MANIFEST_TYPES).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(401, response)) => {
+            let challenge = response
+                .header("www-authenticate")
+                .ok_or(anyhow!("Registry wants authentication but didn't say how"))?;
+            let token = fetch_token(challenge)?;
+
+            ureq::head(&url)
+                .set("Accept", MANIFEST_TYPES)
+                .set("Authorization", &format!("Bearer {}", token))
+                .call()?
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    response
+        .header("docker-content-digest")
+        .map(|digest| digest.to_string())
+        .ok_or(anyhow!("Registry didn't return a digest for {}", url))
+}
+
+pub fn local_digests(image: &str, config: &config::Config) -> Result<Vec<String>> {
+    let output = config
+        .container_command("image")
+        .arg("inspect")
+        .arg("--format={{json .RepoDigests}}")
+        .arg(image)
+        .output()?;
+
+    if !output.status.success() {
+        return Ok(vec![]);
+    }
+
+    let repo_digests: Vec<String> =
+        serde_json::from_slice(&output.stdout).with_context(|| "While parsing RepoDigests")?;
+
+    Ok(repo_digests
+        .iter()
+        .filter_map(|repo_digest| repo_digest.split_once('@'))
+        .map(|(_, digest)| digest.to_string())
+        .collect())
+}
+
+/// Asks the registry about image, returning its newer digest if there is one
+fn check_image(config: &config::Config, image_name: &str) -> Result<Option<String>> {
+    let image = ImageReference::parse(image_name);
+
+    if image.digest.is_some() {
+        println!("{} is pinned by digest.", image_name);
+        return Ok(None);
+    }
+
+    let remote = remote_digest(&image)
+        .with_context(|| format!("Couldn't check registry for {}", image_name))?;
+    let local = local_digests(image_name, config)?;
+
+    debug!("Remote digest: {}, local digests: {:?}", remote, local);
+
+    if local.contains(&remote) {
+        println!("{} is up to date.", image_name);
+        return Ok(None);
+    }
+
+    Ok(Some(remote))
+}
+
+pub fn check_updates_command(args: &CheckUpdatesArgs, config: &config::Config) -> Result<()> {
+    if args.pull && !config.can(Capability::Nightly) {
+        return Err(anyhow!("Only sysops can pull new images!"));
+    }
+
+    let mut pulled = false;
+    let mut failed = false;
+
+    for image in config.images() {
+        let remote = match check_image(config, &image) {
+            Ok(Some(remote)) => remote,
+            Ok(None) => continue,
+            Err(err) => {
+                eprintln!("{:#}", err);
+                failed = true;
+                continue;
+            }
+        };
+
+        info!("Update available for {}: {}", image, remote);
+        println!("An update is available for {} ({})", image, remote);
+
+        let detail = format!("{} ({})", image, remote);
+
+        for name in config.door_names() {
+            let door = config.get_door(&name)?;
+
+            if config.door_image(&door) == image {
+                webhook::notify(
+                    config,
+                    WebhookEvent::Update,
+                    &door.name,
+                    None,
+                    Some(&detail),
+                );
+            }
+        }
+
+        if args.pull {
+            pull::pull_image(config, &image)?;
+            pulled = true;
+        }
+    }
+
+    if pulled {
+        println!("New sessions will use the updated images; running sessions are left alone.");
+    }
+
+    if failed {
+        return Err(anyhow!("Couldn't check every image for updates"));
+    }
+
+    Ok(())
+}
@@ -1,8 +1,11 @@
-use anyhow::{anyhow, Result};
-use log::info;
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
 use nix::unistd;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::env;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
 
 #[derive(Serialize, Debug, Clone)]
 pub struct User {
@@ -10,17 +13,46 @@ pub struct User {
     pub username: String,
     pub display_name: String,
 }
+fn decode_gecos(pwent: &unistd::User) -> String {
+    match pwent.gecos.to_str() {
+        Ok(gecos) => gecos.to_string(),
+        Err(_) => {
+            // Not UTF-8; assume it's Latin-1 like most old passwd files
+            let gecos: String = pwent
+                .gecos
+                .as_bytes()
+                .iter()
+                .map(|byte| char::from(*byte))
+                .collect();
+
+            warn!(
+                "GECOS field for '{}' isn't valid UTF-8, reading it as Latin-1: {}",
+                pwent.name, gecos
+            );
+
+            gecos
+        }
+    }
+}
+
 impl User {
     fn from_pwent(pwent: &unistd::User) -> Result<User> {
-        let gecos = pwent.gecos.clone().into_string()?;
-        let gecos_name = gecos.split(",").next().unwrap_or("");
+        let gecos = decode_gecos(pwent);
+        let gecos_name = gecos.split(',').next().unwrap_or("").trim();
 
-        let display_name = if gecos.is_empty() {
+        let display_name = if gecos_name.is_empty() {
             pwent.name.clone()
         } else {
             gecos_name.to_string()
         };
 
+        if pwent.name.contains(char::REPLACEMENT_CHARACTER) {
+            warn!(
+                "Username for UID {} isn't valid UTF-8: {}",
+                pwent.uid, pwent.name
+            );
+        }
+
         Ok(User {
             uid: pwent.uid.as_raw(),
             username: pwent.name.clone(),
@@ -40,16 +72,53 @@ impl User {
         User::from_pwent(&pwent)
     }
 
+    /// Whether the user belongs to the Unix group named group, primarily or otherwise
+    pub fn in_group(&self, group: &str) -> bool {
+        let Ok(Some(group)) = unistd::Group::from_name(group) else {
+            return false;
+        };
+
+        group.mem.contains(&self.username)
+            || unistd::User::from_uid(unistd::Uid::from_raw(self.uid))
+                .ok()
+                .flatten()
+                .is_some_and(|pwent| pwent.gid == group.gid)
+    }
+
     pub fn from_current_uid() -> Result<User> {
         let uid = unistd::getuid();
         User::from_uid(uid)
     }
 
     pub fn calling_user() -> Result<User> {
-        if let Ok(sudo_user) = env::var("SUDO_USER") {
-            info!("Using username '{}' from SUDO_USER", sudo_user);
-            User::from_username(&sudo_user)
-        } else if let Ok(doas_user) = env::var("DOAS_USER") {
+        if let Some(sudo_user) = env::var_os("SUDO_USER") {
+            match sudo_user.into_string() {
+                Ok(sudo_user) => {
+                    info!("Using username '{}' from SUDO_USER", sudo_user);
+                    User::from_username(&sudo_user)
+                }
+                Err(sudo_user) => {
+                    warn!(
+                        "SUDO_USER '{}' isn't valid UTF-8, using SUDO_UID instead",
+                        sudo_user.to_string_lossy()
+                    );
+
+                    let uid = env::var("SUDO_UID")
+                        .with_context(|| "SUDO_UID isn't set")?
+                        .parse::<u32>()
+                        .with_context(|| "SUDO_UID isn't a number")?;
+
+                    User::from_uid(unistd::Uid::from_raw(uid))
+                }
+            }
+        } else if let Some(doas_user) = env::var_os("DOAS_USER") {
+            let doas_user = doas_user.into_string().map_err(|doas_user| {
+                anyhow!(
+                    "DOAS_USER '{}' isn't valid UTF-8",
+                    doas_user.to_string_lossy()
+                )
+            })?;
+
             info!("Using username '{}' from DOAS_USER", doas_user);
             User::from_username(&doas_user)
         } else {
@@ -58,3 +127,72 @@ impl User {
         }
     }
 }
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct DisplayNamePolicy {
+    /// Longest display name to hand to doors; longer names are truncated
+    max_length: Option<usize>,
+
+    /// Punctuation allowed in display names, i.e. "-_.'"; letters, digits and
+    /// spaces are always allowed. Any character is allowed if this isn't set.
+    allowed_characters: Option<String>,
+
+    /// Program that reads a display name on stdin and prints the name to use
+    /// instead, i.e. a profanity filter. A non-zero exit rejects the name.
+    filter_command: Option<PathBuf>,
+}
+impl DisplayNamePolicy {
+    fn run_filter(filter_command: &PathBuf, name: &str) -> Result<String> {
+        let mut child = Command::new(filter_command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Couldn't run {}", filter_command.display()))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(name.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+
+        if !output.status.success() {
+            return Err(anyhow!("Sorry, the name '{}' isn't allowed here.", name));
+        }
+
+        let filtered = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        Ok(if filtered.is_empty() {
+            name.to_string()
+        } else {
+            filtered
+        })
+    }
+
+    pub fn apply(&self, user: &User) -> Result<String> {
+        let mut name: String = user
+            .display_name
+            .chars()
+            .filter(|c| !c.is_control())
+            .filter(|c| match &self.allowed_characters {
+                Some(allowed) => c.is_alphanumeric() || *c == ' ' || allowed.contains(*c),
+                None => true,
+            })
+            .collect();
+
+        if let Some(filter_command) = &self.filter_command {
+            name = DisplayNamePolicy::run_filter(filter_command, &name)?;
+        }
+
+        if let Some(max_length) = self.max_length {
+            name = name.chars().take(max_length).collect();
+        }
+
+        let name = name.trim();
+
+        Ok(if name.is_empty() {
+            user.username.clone()
+        } else {
+            name.to_string()
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use super::config;
+use super::door;
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+pub fn validate_command() -> Result<()> {
+    let config_path = config::config_path();
+    let text = fs::read_to_string(&config_path)
+        .with_context(|| format!("Couldn't read config file: {}", config_path.display()))?;
+
+    let mut problems = 0;
+
+    let mut unknown: Vec<(PathBuf, config::UnknownKey)> = config::unknown_keys(&config_path, &text)
+        .with_context(|| format!("Couldn't parse config file: {}", config_path.display()))?
+        .into_iter()
+        .map(|key| (config_path.clone(), key))
+        .collect();
+
+    for path in config::door_files(&config::doors_dir()?)? {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read door file: {}", path.display()))?;
+
+        for key in config::unknown_door_keys(&path, &text)
+            .with_context(|| format!("Couldn't parse door file: {}", path.display()))?
+        {
+            unknown.push((path.clone(), key));
+        }
+    }
+
+    for (path, key) in unknown {
+        problems += 1;
+
+        match key.suggestion {
+            Some(suggestion) => println!(
+                "{}: unknown setting '{}'; did you mean '{}'?",
+                path.display(),
+                key.path,
+                suggestion
+            ),
+            None => println!("{}: unknown setting '{}'", path.display(), key.path),
+        }
+    }
+
+    let config = config::Config::load()?;
+
+    for name in config.door_names() {
+        let door = config.get_door(&name)?;
+
+        if let Err(err) = fs::read_dir(&door.options.door_path) {
+            problems += 1;
+            println!(
+                "{}: can't read door_path {}: {}",
+                name,
+                door.options.door_path.display(),
+                err
+            );
+        }
+
+        if let Some(terminfo) = &door.options.terminfo {
+            if !terminfo.is_file() {
+                problems += 1;
+                println!("{}: terminfo {} doesn't exist", name, terminfo.display());
+            }
+        }
+
+        if door.options.network.is_none() {
+            for address in door
+                .options
+                .requires
+                .iter()
+                .filter_map(|requirement| requirement.tcp.as_ref())
+            {
+                problems += 1;
+                println!(
+                    "{}: requires tcp {}, but has no network; set network to reach it",
+                    name, address
+                );
+            }
+        }
+
+        if let Err(err) = door.options.translate.check() {
+            problems += 1;
+            println!("{}: {:#}", name, err);
+        }
+
+        for dropfile in door.options.dropfiles.iter() {
+            if let Err(err) = dropfile.filename(door.options.last_node()) {
+                problems += 1;
+                println!("{}: {:#}", name, err);
+            }
+        }
+
+        if let Err(err) = door::render_launch_batch(&config, &door, door.options.last_node()) {
+            problems += 1;
+            println!("{}: launch_commands don't render: {:#}", name, err);
+        }
+
+        let nodes = match door.options.node_numbers() {
+            Ok(nodes) => nodes,
+            Err(err) => {
+                problems += 1;
+                println!("{}: {:#}", name, err);
+                continue;
+            }
+        };
+
+        if nodes.is_empty() || nodes.iter().any(|node| *node < 1) {
+            problems += 1;
+            println!("{}: node numbers have to start at 1 or higher", name);
+        }
+
+        let mut unique = nodes.clone();
+
+        unique.sort();
+        unique.dedup();
+
+        if unique.len() != nodes.len() {
+            problems += 1;
+            println!("{}: nodes lists the same node more than once", name);
+        }
+
+        for node in door.options.reserved_nodes.keys() {
+            if !nodes.contains(node) {
+                problems += 1;
+                println!("{}: reserved node {} isn't one of its nodes", name, node);
+            }
+        }
+    }
+
+    if problems > 0 {
+        return Err(anyhow!(
+            "Found {} problem{} in {}",
+            problems,
+            if problems == 1 { "" } else { "s" },
+            config_path.display()
+        ));
+    }
+
+    println!("{} looks good.", config_path.display());
+
+    Ok(())
+}
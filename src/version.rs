@@ -0,0 +1,75 @@
+use super::config;
+use super::container;
+use super::VersionArgs;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize, Debug)]
+struct VersionInfo {
+    version: &'static str,
+    git_commit: &'static str,
+    build_date: Option<DateTime<Utc>>,
+    features: Vec<&'static str>,
+    backends: Vec<&'static str>,
+    engine_path: Option<PathBuf>,
+    engine_version: Option<String>,
+    config_path: PathBuf,
+}
+impl VersionInfo {
+    fn gather() -> VersionInfo {
+        let build_date = env!("DOORMAN_BUILD_TIMESTAMP")
+            .parse::<i64>()
+            .ok()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0));
+
+        let features = env!("DOORMAN_FEATURES")
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .collect();
+
+        let engine_path = container::find_engine().ok();
+        let engine_version = engine_path
+            .as_ref()
+            .and_then(|path| container::engine_version(path).ok());
+
+        VersionInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            git_commit: env!("DOORMAN_GIT_COMMIT"),
+            build_date,
+            features,
+            backends: vec!["podman", "docker"],
+            engine_path,
+            engine_version,
+            config_path: config::config_path(),
+        }
+    }
+}
+
+pub fn version_command(args: &VersionArgs) -> Result<()> {
+    let info = VersionInfo::gather();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+
+    println!("doorman {} ({})", info.version, info.git_commit);
+
+    if let Some(build_date) = info.build_date {
+        println!("Built: {}", build_date.format("%F %T UTC"));
+    }
+
+    if !info.features.is_empty() {
+        println!("Features: {}", info.features.join(", "));
+    }
+
+    println!(
+        "Engine: {}",
+        info.engine_version.unwrap_or("not found".to_string())
+    );
+    println!("Config: {}", info.config_path.display());
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use super::config::{self, Capability};
+use super::door;
+use super::who;
+use super::{SnoopArgs, WatchArgs};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+fn session_running(config: &config::Config, door_name: &str, node: i8) -> bool {
+    who::who(&Some(door_name.to_string()), config)
+        .map(|nodes| nodes.iter().any(|session| session.node == Some(node)))
+        .unwrap_or(false)
+}
+
+/// Mirrors a session's recording to stdout until the session ends; never sends input
+fn follow(config: &config::Config, door: &config::Door, node: Option<i8>) -> Result<()> {
+    let sessions: Vec<who::WhoNode> = who::who(&Some(door.name.clone()), config)?
+        .into_iter()
+        .filter(|session| session.node.is_some())
+        .filter(|session| node.is_none() || session.node == node)
+        .collect();
+
+    let session = match sessions.as_slice() {
+        [] if node.is_some() => {
+            return Err(anyhow!(
+                "Nobody is playing {} on node {} right now.",
+                door.name,
+                node.unwrap_or(0)
+            ))
+        }
+        [] => return Err(anyhow!("Nobody is playing {} right now.", door.name)),
+        [session] => session,
+        _ => {
+            return Err(anyhow!(
+                "Several people are playing {}; pick one with --node",
+                door.name
+            ))
+        }
+    };
+
+    let node = session.node.unwrap_or(0);
+    let log_path = door::session_log_path(config, &door.name, node);
+    let mut log = fs::File::open(&log_path)
+        .with_context(|| format!("Node {} of {} isn't being recorded", node, door.name))?;
+
+    eprintln!(
+        "Watching {} play {} on node {}. Press Ctrl-C to stop.",
+        session.user, door.name, node
+    );
+
+    let mut stdout = io::stdout();
+    let mut buffer = [0u8; 4096];
+    let mut last_check = Instant::now();
+
+    loop {
+        let count = log.read(&mut buffer)?;
+
+        if count > 0 {
+            stdout.write_all(&buffer[..count])?;
+            stdout.flush()?;
+            continue;
+        }
+
+        if last_check.elapsed() >= SESSION_CHECK_INTERVAL {
+            if !session_running(config, &door.name, node) {
+                break;
+            }
+
+            last_check = Instant::now();
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    eprintln!();
+    eprintln!("{} has left {}.", session.user, door.name);
+
+    Ok(())
+}
+
+pub fn watch_command(args: &WatchArgs, config: &config::Config) -> Result<()> {
+    let door = config.get_door(&args.door)?;
+
+    if !config.can(Capability::Kick) && !door.options.spectators {
+        return Err(anyhow!("Spectating isn't allowed on {}.", door.name));
+    }
+
+    follow(config, &door, args.node)
+}
+
+/// Like watch, but for sysops, and for a node even if the door doesn't allow spectators
+pub fn snoop_command(args: &SnoopArgs, config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Kick) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    let door = config.get_door(&args.door)?;
+
+    follow(config, &door, Some(args.node))
+}
@@ -0,0 +1,163 @@
+use super::config;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    /// Someone started playing a door
+    Launch,
+
+    /// Someone finished playing a door
+    Exit,
+
+    /// A door's nightly maintenence finished, successfully or not
+    Nightly,
+
+    /// Launching a door or running a sysop command failed
+    Error,
+
+    /// check-updates found a newer image for a door
+    Update,
+}
+
+fn all_events() -> Vec<WebhookEvent> {
+    vec![
+        WebhookEvent::Launch,
+        WebhookEvent::Exit,
+        WebhookEvent::Nightly,
+        WebhookEvent::Error,
+        WebhookEvent::Update,
+    ]
+}
+
+/// Where to POST JSON when things happen
+#[derive(Deserialize, Debug, Clone)]
+pub struct WebhookOptions {
+    /// URL to POST to, i.e. a Discord webhook
+    pub url: String,
+
+    #[serde(default = "all_events")]
+    /// Events to send; defaults to all of them
+    pub events: Vec<WebhookEvent>,
+
+    #[serde(default)]
+    /// Only send events for these doors; defaults to every door
+    pub doors: Vec<String>,
+
+    #[serde(default)]
+    /// Extra HTTP headers to send, i.e. for authentication
+    pub headers: HashMap<String, String>,
+}
+impl WebhookOptions {
+    fn wants(&self, event: WebhookEvent, door: &str) -> bool {
+        self.events.contains(&event)
+            && (self.doors.is_empty() || self.doors.iter().any(|name| name == door))
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct Payload<'a> {
+    /// A one-line summary, which is what Discord shows
+    content: String,
+    event: WebhookEvent,
+    door: &'a str,
+    node: Option<i8>,
+    user: &'a str,
+    display_name: &'a str,
+    time: DateTime<Utc>,
+    detail: Option<&'a str>,
+}
+
+fn summary(
+    config: &config::Config,
+    event: WebhookEvent,
+    door: &str,
+    node: Option<i8>,
+    detail: Option<&str>,
+) -> String {
+    let on_node = node.map_or(String::new(), |node| format!(" on node {}", node));
+
+    match (event, detail) {
+        (WebhookEvent::Launch, _) => format!(
+            "{} started playing {}{}",
+            config.user.display_name, door, on_node
+        ),
+        (WebhookEvent::Exit, _) => format!(
+            "{} finished playing {}{}",
+            config.user.display_name, door, on_node
+        ),
+        (WebhookEvent::Nightly, None) => format!("Nightly maintenence for {} finished", door),
+        (WebhookEvent::Nightly, Some(detail)) => {
+            format!("Nightly maintenence for {} failed: {}", door, detail)
+        }
+        (WebhookEvent::Error, detail) => format!(
+            "Error with {}{}: {}",
+            door,
+            on_node,
+            detail.unwrap_or("unknown")
+        ),
+        (WebhookEvent::Update, detail) => format!(
+            "An update is available for {}: {}",
+            door,
+            detail.unwrap_or("unknown")
+        ),
+    }
+}
+
+/// POSTs event to every webhook that wants it; failures are only logged, never fatal
+pub fn notify(
+    config: &config::Config,
+    event: WebhookEvent,
+    door: &str,
+    node: Option<i8>,
+    detail: Option<&str>,
+) {
+    let webhooks: Vec<&WebhookOptions> = config
+        .webhooks
+        .iter()
+        .filter(|webhook| webhook.wants(event, door))
+        .collect();
+
+    if webhooks.is_empty() {
+        return;
+    }
+
+    let payload = Payload {
+        content: summary(config, event, door, node, detail),
+        event,
+        door,
+        node,
+        user: &config.user.username,
+        display_name: &config.user.display_name,
+        time: Utc::now(),
+        detail,
+    };
+
+    let body = match serde_json::to_string(&payload) {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("Couldn't encode webhook payload: {}", err);
+            return;
+        }
+    };
+
+    for webhook in webhooks {
+        let mut request = ureq::post(&webhook.url)
+            .timeout(Duration::from_secs(2))
+            .set("Content-Type", "application/json");
+
+        for (name, value) in webhook.headers.iter() {
+            request = request.set(name, value);
+        }
+
+        debug!("Sending {:?} webhook to {}", event, webhook.url);
+
+        if let Err(err) = request.send_string(&body) {
+            warn!("Couldn't send webhook to {}: {}", webhook.url, err);
+        }
+    }
+}
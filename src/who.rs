@@ -1,15 +1,20 @@
+use super::charset;
 use super::config;
-use super::{OutputFormat, WhoArgs};
-use anyhow::{anyhow, Result};
+use super::list;
+use super::session;
+use super::{Charset, OutputFormat, WhoArgs};
+use anyhow::{anyhow, Context, Result};
 use chrono::serde::ts_seconds;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use chrono_humanize::{Accuracy, HumanTime, Tense};
-use comfy_table::modifiers::{UTF8_ROUND_CORNERS, UTF8_SOLID_INNER_BORDERS};
-use comfy_table::presets::UTF8_FULL;
 use comfy_table::{Cell, Table};
+use log::debug;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
 
 #[derive(Deserialize, Debug)]
 struct DockerPS {
@@ -39,14 +44,32 @@ struct PodmanPS {
 pub struct WhoNode {
     pub container_id: String,
     pub user: String,
+    pub display_name: Option<String>,
     pub door: String,
     pub node: Option<i8>,
     pub command: Option<String>,
+    pub health: Option<String>,
+    pub impersonated_by: Option<String>,
+    pub nowait: Option<bool>,
+    pub source: Option<String>,
+
+    /// Minutes the session may last
+    pub time_limit: Option<u32>,
+
+    /// Minutes until the session hits its time limit
+    pub time_left: Option<i64>,
 
     #[serde(with = "ts_seconds")]
     pub since: DateTime<Utc>,
 }
 
+/// Minutes a session that started at since has left of time_limit
+pub fn time_left(since: DateTime<Utc>, time_limit: Option<u32>) -> Option<i64> {
+    let limit = chrono::Duration::minutes(time_limit?.into());
+
+    Some((since + limit - Utc::now()).num_minutes().max(0))
+}
+
 fn split_docker_label(label: &str) -> (&str, &str) {
     let mut splitter = label.splitn(2, "=");
 
@@ -59,21 +82,53 @@ fn split_docker_label(label: &str) -> (&str, &str) {
     (key, "")
 }
 
+/// Splits docker's "key=value,key=value" labels; a piece without an = is the rest of
+/// a value that had a comma in it, i.e. a display name like "Public, Joan Q."
+fn split_docker_labels(labels: &str) -> Vec<(&str, String)> {
+    let mut split: Vec<(&str, String)> = vec![];
+
+    for label in labels.split(",") {
+        match split.last_mut() {
+            Some((_, value)) if !label.contains('=') => {
+                value.push(',');
+                value.push_str(label);
+            }
+            _ => {
+                let (key, value) = split_docker_label(label);
+                split.push((key, value.to_string()));
+            }
+        }
+    }
+
+    split
+}
+
 fn parse_docker_line(line: &str) -> Result<WhoNode> {
     let parsed: DockerPS = serde_json::from_str(line)?;
 
     let mut user: Option<String> = None;
+    let mut display_name: Option<String> = None;
     let mut door: Option<String> = None;
     let mut node: Option<i8> = None;
     let mut command: Option<String> = None;
+    let mut impersonated_by: Option<String> = None;
+    let mut nowait: Option<bool> = None;
+    let mut source: Option<String> = None;
+    let mut time_limit: Option<u32> = None;
+
+    for (key, value) in split_docker_labels(&parsed.labels) {
+        let value = value.as_str();
 
-    for label in parsed.labels.split(",") {
-        let (key, value) = split_docker_label(label);
         match key {
             "doorman.user" => user = Some(String::from(value)),
+            "doorman.display_name" => display_name = Some(String::from(value)),
             "doorman.door" => door = Some(String::from(value)),
             "doorman.node" => node = Some(value.parse::<i8>().unwrap()),
             "doorman.command" => command = Some(String::from(value)),
+            "doorman.impersonated_by" => impersonated_by = Some(String::from(value)),
+            "doorman.nowait" => nowait = value.parse::<bool>().ok(),
+            "doorman.source" => source = Some(String::from(value)),
+            "doorman.time_limit" => time_limit = value.parse::<u32>().ok(),
             _ => (),
         }
     }
@@ -84,9 +139,16 @@ fn parse_docker_line(line: &str) -> Result<WhoNode> {
         Ok(WhoNode {
             container_id: parsed.container_id,
             user,
+            display_name,
             door,
             node,
             command,
+            health: None,
+            impersonated_by,
+            nowait,
+            source,
+            time_limit,
+            time_left: time_left(since, time_limit),
             since,
         })
     } else {
@@ -109,18 +171,32 @@ fn parse_docker(output: &str) -> Vec<WhoNode> {
 fn parse_podman_container(container: &PodmanPS) -> Result<WhoNode> {
     let labels = container.labels.clone().unwrap();
     let user = labels.get("doorman.user");
+    let display_name = labels.get("doorman.display_name");
     let door = labels.get("doorman.door");
     let node = labels.get("doorman.node");
     let command = labels.get("doorman.command");
+    let impersonated_by = labels.get("doorman.impersonated_by");
+    let nowait = labels.get("doorman.nowait");
+    let source = labels.get("doorman.source");
+    let time_limit = labels
+        .get("doorman.time_limit")
+        .and_then(|value| value.parse::<u32>().ok());
     let since = DateTime::from_timestamp(container.created_ts, 0).unwrap();
 
     if let (Some(user), Some(door)) = (user, door) {
         Ok(WhoNode {
             container_id: container.container_id.clone(),
             user: user.clone(),
+            display_name: display_name.cloned(),
             door: door.clone(),
             node: node.map(|value| value.parse::<i8>().unwrap()),
-            command: command.map(|value| value.clone()),
+            command: command.cloned(),
+            health: None,
+            impersonated_by: impersonated_by.cloned(),
+            nowait: nowait.and_then(|value| value.parse::<bool>().ok()),
+            source: source.cloned(),
+            time_limit,
+            time_left: time_left(since, time_limit),
             since,
         })
     } else {
@@ -141,7 +217,7 @@ fn parse_podman(output: &str) -> Result<Vec<WhoNode>> {
     Ok(nodes)
 }
 
-fn parse_ps(output: &str) -> Vec<WhoNode> {
+pub fn parse_ps(output: &str) -> Vec<WhoNode> {
     if let Ok(nodes) = parse_podman(output) {
         nodes
     } else {
@@ -149,16 +225,56 @@ fn parse_ps(output: &str) -> Vec<WhoNode> {
     }
 }
 
-fn who(door: &Option<String>, config: &config::Config) -> Result<Vec<WhoNode>> {
-    let mut ps = config.container_command("ps");
+fn inspect_health(nodes: &mut [WhoNode], config: &config::Config) -> Result<()> {
+    if nodes.is_empty() {
+        return Ok(());
+    }
 
-    ps.arg("ps")
-        .arg("--format=json")
-        .arg("--filter")
-        .arg(door.clone().map_or_else(
-            || "label=doorman.door".to_string(),
-            |door| format!("label=doorman.door={}", door),
+    let mut inspect = config.container_command("inspect");
+
+    inspect.arg("--format={{.Id}} {{if .State.Health}}{{.State.Health.Status}}{{end}}");
+
+    for node in nodes.iter() {
+        inspect.arg(&node.container_id);
+    }
+
+    let output = inspect.output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "'{} inspect' exited with status {}",
+            inspect.get_program().to_string_lossy(),
+            output.status
         ));
+    }
+
+    let stdout = String::from_utf8(output.stdout)?;
+
+    for line in stdout.lines() {
+        let (id, status) = line.split_once(' ').unwrap_or((line, ""));
+
+        if status.is_empty() {
+            continue;
+        }
+
+        for node in nodes.iter_mut() {
+            if id.starts_with(&node.container_id) || node.container_id.starts_with(id) {
+                node.health = Some(status.to_string());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks the container engine who's playing
+fn ps_who(door: &Option<String>, config: &config::Config) -> Result<Vec<WhoNode>> {
+    let mut ps = config.ps_command();
+
+    ps.arg("--filter").arg(door.clone().map_or_else(
+        || "label=doorman.door".to_string(),
+        |door| format!("label=doorman.door={}", door),
+    ));
 
     let output = ps.output()?;
 
@@ -180,6 +296,28 @@ fn who(door: &Option<String>, config: &config::Config) -> Result<Vec<WhoNode>> {
     let stdout = String::from_utf8(output.stdout)?;
     let mut nodes = parse_ps(&stdout);
 
+    if let Err(err) = inspect_health(&mut nodes, config) {
+        debug!("Couldn't check container health: {:#}", err);
+    }
+
+    Ok(nodes)
+}
+
+pub fn who(door: &Option<String>, config: &config::Config) -> Result<Vec<WhoNode>> {
+    // Players can't always talk to the engine, i.e. on a host with rootful docker
+    let mut nodes = match ps_who(door, config) {
+        Ok(nodes) => nodes,
+        Err(err) => {
+            debug!("Falling back to session files: {:#}", err);
+
+            session::sessions(config, door)
+                .with_context(|| format!("{:#}", err))?
+                .into_iter()
+                .map(WhoNode::from)
+                .collect()
+        }
+    };
+
     nodes.sort_by(|a, b| match a.door.cmp(&b.door) {
         Ordering::Equal => a.node.unwrap_or(0).cmp(&b.node.unwrap_or(0)),
         other => other,
@@ -188,13 +326,146 @@ fn who(door: &Option<String>, config: &config::Config) -> Result<Vec<WhoNode>> {
     Ok(nodes)
 }
 
-fn print_who(format: &Option<OutputFormat>, nodes: &Vec<WhoNode>) -> Result<()> {
+fn new_table(charset: Charset, header: Vec<&str>) -> Table {
+    let mut table = charset::new_table(charset);
+
+    table.set_header(header);
+
+    table
+}
+
+fn duration(node: &WhoNode) -> String {
+    HumanTime::from(Utc::now().signed_duration_since(node.since))
+        .to_text_en(Accuracy::Rough, Tense::Present)
+}
+
+fn players_table(charset: Charset, long: bool, show_health: bool, nodes: &[&WhoNode]) -> Table {
+    let show_source = nodes.iter().any(|node| node.source.is_some());
+    let show_name = nodes.iter().any(|node| node.display_name.is_some());
+    let show_time_left = nodes.iter().any(|node| node.time_left.is_some());
+    let mut header = vec!["User"];
+
+    if show_name {
+        header.push("Name");
+    }
+
+    header.extend(["Door", "Node", "Duration"]);
+
+    if show_time_left {
+        header.push("Time left");
+    }
+
+    if show_source {
+        header.push("Source");
+    }
+
+    if show_health {
+        header.push("Health");
+    }
+
+    if long {
+        header.push("Impersonated by");
+        header.push("Container");
+    }
+
+    let mut table = new_table(charset, header);
+
+    for node in nodes {
+        let mut row = vec![Cell::new(&node.user)];
+
+        if show_name {
+            row.push(Cell::new(
+                node.display_name.clone().unwrap_or("-".to_string()),
+            ));
+        }
+
+        row.extend([
+            Cell::new(&node.door),
+            Cell::new(node.node.map_or("???".to_string(), |i| i.to_string())),
+            Cell::new(duration(node)),
+        ]);
+
+        if show_time_left {
+            row.push(Cell::new(
+                node.time_left
+                    .map_or("-".to_string(), |minutes| format!("{} min", minutes)),
+            ));
+        }
+
+        if show_source {
+            row.push(Cell::new(node.source.clone().unwrap_or("-".to_string())));
+        }
+
+        if show_health {
+            row.push(Cell::new(node.health.clone().unwrap_or("-".to_string())));
+        }
+
+        if long {
+            row.push(Cell::new(
+                node.impersonated_by.clone().unwrap_or("-".to_string()),
+            ));
+            row.push(Cell::new(&node.container_id));
+        }
+
+        table.add_row(row);
+    }
+
+    table
+}
+
+fn maintenance_table(charset: Charset, long: bool, show_health: bool, nodes: &[&WhoNode]) -> Table {
+    let mut header = vec!["Sysop", "Door", "Task", "Duration", "Mode"];
+
+    if show_health {
+        header.push("Health");
+    }
+
+    if long {
+        header.push("Container");
+    }
+
+    let mut table = new_table(charset, header);
+
+    for node in nodes {
+        let mut row = vec![
+            Cell::new(&node.user),
+            Cell::new(&node.door),
+            Cell::new(node.command.clone().unwrap_or("???".to_string())),
+            Cell::new(duration(node)),
+            Cell::new(match node.nowait {
+                Some(true) => "nowait",
+                Some(false) => "blocking",
+                None => "-",
+            }),
+        ];
+
+        if show_health {
+            row.push(Cell::new(node.health.clone().unwrap_or("-".to_string())));
+        }
+
+        if long {
+            row.push(Cell::new(&node.container_id));
+        }
+
+        table.add_row(row);
+    }
+
+    table
+}
+
+fn print_who(
+    format: &Option<OutputFormat>,
+    charset: Charset,
+    long: bool,
+    nodes: &Vec<WhoNode>,
+) -> Result<()> {
     if let Some(format) = format {
         println!(
             "{}",
             match format {
-                OutputFormat::JSON => serde_json::to_string(&nodes)?,
-                OutputFormat::YAML => serde_yaml::to_string(&nodes)?,
+                OutputFormat::Json => serde_json::to_string(&nodes)?,
+                OutputFormat::Yaml => serde_yaml::to_string(&nodes)?,
+                OutputFormat::Csv => list::to_csv(nodes)?,
             }
         );
 
@@ -206,37 +477,69 @@ fn print_who(format: &Option<OutputFormat>, nodes: &Vec<WhoNode>) -> Result<()>
         return Ok(());
     }
 
-    let mut table = Table::new();
-
-    table
-        .load_preset(UTF8_FULL)
-        .apply_modifier(UTF8_ROUND_CORNERS)
-        .apply_modifier(UTF8_SOLID_INNER_BORDERS)
-        .set_header(vec!["User", "Door", "Node", "Duration"]);
+    let show_health = nodes.iter().any(|node| node.health.is_some());
+    let (maintenance, players): (Vec<&WhoNode>, Vec<&WhoNode>) =
+        nodes.iter().partition(|node| node.command.is_some());
 
-    for node in nodes {
-        let duration = HumanTime::from(Utc::now().signed_duration_since(node.since));
+    if !players.is_empty() {
+        let table = players_table(charset, long, show_health, &players);
 
-        table.add_row(vec![
-            Cell::new(&node.user),
-            Cell::new(&node.door),
-            Cell::new(node.node.map_or_else(
-                || node.command.clone().unwrap_or("???".to_string()),
-                |i| i.to_string(),
-            )),
-            Cell::new(duration.to_text_en(Accuracy::Rough, Tense::Present)),
-        ]);
+        charset::print_line(charset, &table.to_string())?;
     }
 
-    println!("{table}");
+    if !maintenance.is_empty() {
+        if !players.is_empty() {
+            println!();
+        }
+
+        println!("Maintenance:");
+        let table = maintenance_table(charset, long, show_health, &maintenance);
+
+        charset::print_line(charset, &table.to_string())?;
+    }
 
     Ok(())
 }
 
+fn who_once(args: &WhoArgs, config: &config::Config) -> Result<()> {
+    let mut nodes = who(&args.door, config)?;
+
+    if let Some(category) = &args.category {
+        nodes.retain(|node| config.in_category(&node.door, category));
+    }
+
+    if let Some(user) = &args.user {
+        nodes.retain(|node| &node.user == user);
+    }
+
+    print_who(
+        &args.format,
+        charset::detect(args.charset),
+        args.long,
+        &nodes,
+    )
+}
+
 pub fn who_command(args: &WhoArgs, config: &config::Config) -> Result<()> {
-    let nodes = who(&args.door, config)?;
+    let Some(seconds) = args.watch else {
+        return who_once(args, config);
+    };
 
-    print_who(&args.format, &nodes)?;
+    let interval = Duration::from_secs(seconds.max(1));
 
-    Ok(())
+    loop {
+        // Clear the screen and go home
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "{} (every {}s; Ctrl-C to stop)",
+            Local::now().format("%F %T"),
+            interval.as_secs()
+        );
+        println!();
+
+        who_once(args, config)?;
+        io::stdout().flush()?;
+
+        thread::sleep(interval);
+    }
 }
@@ -0,0 +1,151 @@
+use super::config::{self, Capability};
+use super::editor;
+use super::menu;
+use anyhow::{anyhow, Context, Result};
+use serde_yaml::{Mapping, Value};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+
+/// Asks until it gets an answer, or returns default for an empty one
+fn ask(question: &str, default: Option<&str>) -> Result<String> {
+    loop {
+        let answer = match default {
+            Some(default) => menu::prompt(&format!("{} [{}]", question, default))?,
+            None => menu::prompt(question)?,
+        };
+
+        if !answer.is_empty() {
+            return Ok(answer);
+        }
+
+        if let Some(default) = default {
+            return Ok(default.to_string());
+        }
+    }
+}
+
+/// Asks a question that can be skipped by not answering
+fn ask_optional(question: &str) -> Result<Option<String>> {
+    let answer = menu::prompt(&format!("{} (optional)", question))?;
+
+    Ok((!answer.is_empty()).then_some(answer))
+}
+
+fn ask_name(existing: &Value) -> Result<String> {
+    loop {
+        let name = ask("Door name (used on the command line, i.e. lord):", None)?;
+
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            println!("Door names can only have letters, digits, - and _.");
+        } else if existing
+            .get("doors")
+            .and_then(|doors| doors.get(name.as_str()))
+            .is_some()
+            || config::included_door_file(&name)?.is_some()
+        {
+            println!("There's already a door named {}.", name);
+        } else {
+            return Ok(name);
+        }
+    }
+}
+
+fn ask_door_path() -> Result<PathBuf> {
+    loop {
+        let path = PathBuf::from(ask("Path to the door's files:", None)?);
+
+        if path.is_dir() {
+            return Ok(path.canonicalize().unwrap_or(path));
+        }
+
+        println!("{} isn't a directory.", path.display());
+    }
+}
+
+fn ask_max_nodes() -> Result<i8> {
+    loop {
+        match ask("Number of nodes:", Some("1"))?.parse::<i8>() {
+            Ok(nodes) if nodes >= 1 => return Ok(nodes),
+            _ => println!("That should be a number from 1 to 127."),
+        }
+    }
+}
+
+pub fn add_door_command(config: &config::Config) -> Result<()> {
+    if !config.can(Capability::Configure) {
+        return Err(anyhow!("This command is only for sysops!"));
+    }
+
+    if !io::stdin().is_terminal() {
+        return Err(anyhow!("add-door needs a terminal to ask questions"));
+    }
+
+    let config_path = config::config_path();
+
+    if config::is_toml(&config_path) {
+        return Err(anyhow!(
+            "add-door only works with YAML configs; add the door to {} by hand",
+            config_path.display()
+        ));
+    }
+    let original = fs::read_to_string(&config_path)
+        .with_context(|| format!("Couldn't read config file: {}", config_path.display()))?;
+    let existing: Value = serde_yaml::from_str(&original)
+        .with_context(|| format!("Couldn't parse config file: {}", config_path.display()))?;
+
+    let name = ask_name(&existing)?;
+    let mut section = Mapping::new();
+
+    if let Some(description) = ask_optional("Description:")? {
+        section.insert("description".into(), description.into());
+    }
+
+    section.insert(
+        "door_path".into(),
+        ask_door_path()?.display().to_string().into(),
+    );
+    section.insert("max_nodes".into(), ask_max_nodes()?.into());
+    section.insert(
+        "launch_commands".into(),
+        ask("DOS command to launch the door:", None)?.into(),
+    );
+
+    if let Some(commands) = ask_optional("DOS command to configure the door:")? {
+        section.insert("configure_commands".into(), commands.into());
+    }
+
+    if let Some(commands) = ask_optional("DOS command for nightly maintenence:")? {
+        section.insert("nightly_commands".into(), commands.into());
+    }
+
+    if let Some(commands) = ask_optional("DOS command to warm the door up for its first player:")? {
+        section.insert("warmup_commands".into(), commands.into());
+    }
+
+    let updated = editor::add_door(&original, &name, &section)?;
+
+    config::validate(&config_path, &updated)?;
+
+    println!();
+    print!("{}", serde_yaml::to_string(&section)?);
+    println!();
+
+    if !menu::confirm(&format!("Add {} to {}?", name, config_path.display()))? {
+        println!("No changes made.");
+        return Ok(());
+    }
+
+    let backup_path = editor::write_config(&config_path, &updated)?;
+
+    println!(
+        "Added {}; the previous config is in {}",
+        name,
+        backup_path.display()
+    );
+
+    Ok(())
+}